@@ -0,0 +1,51 @@
+//! scanner throughput on a large generated script, run with `cargo bench`,
+//! there is no benchmark framework dependency, so every case is timed over a
+//! fixed number of runs and the best run is reported
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use jlox::scanner::Scanner;
+
+const RUNS: usize = 10;
+
+/// a script of about `size` bytes with a mix of code, strings and comments
+fn generate(size: usize) -> Vec<u8> {
+    let block = "// a line comment that goes on for a while before the code starts\n\
+                 var greeting = \"hello, world\" + \" and ${name} too\";\n\
+                 /* a block comment\n   over two lines */\n\
+                 if (count >= 10 and !done) { print count * 2.5 - offset; }\n";
+    block.repeat(size / block.len() + 1).into_bytes()
+}
+
+fn bench(name: &str, source: &[u8], scan: impl Fn(&[u8]) -> usize) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        tokens = black_box(scan(black_box(source)));
+        best = best.min(start.elapsed());
+    }
+
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<24} {:>10} tokens {:>10.2?} {:>8.1} MB/s",
+        name,
+        tokens,
+        best,
+        megabytes / best.as_secs_f64()
+    );
+}
+
+fn main() {
+    let source = generate(32 * 1024 * 1024);
+
+    bench("scan with trivia", &source, |source| {
+        Scanner::new(source).filter(Result::is_ok).count()
+    });
+    bench("scan skipping trivia", &source, |source| {
+        Scanner::new(source)
+            .skip_trivia(true)
+            .filter(Result::is_ok)
+            .count()
+    });
+}
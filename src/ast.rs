@@ -1,63 +1,763 @@
-use crate::scanner::Token;
+use crate::json::{json_number, json_string};
+use crate::scanner::{Span, Token};
 
+/// visitors are instances, so they can keep state between visits
+/// (environments, collected errors, counters...)
 pub trait Visitor<T: ?Sized> {
     type Return;
-    fn visit(value: &T) -> Self::Return;
+    fn visit(&mut self, value: &T) -> Self::Return;
 }
 
 pub trait AcceptVisitor {
-    fn accept<V: Visitor<Self>>(&self) -> V::Return {
-        V::visit(self)
+    fn accept<V: Visitor<Self>>(&self, visitor: &mut V) -> V::Return {
+        visitor.visit(self)
+    }
+}
+
+/// a pass that only cares about some of the nodes implements `Walk` and overrides
+/// only the `visit_*` functions of those nodes, the default implementations walk
+/// into the node children, an overriding function can call `walk_expr` or
+/// `walk_stmt` to keep walking into the children of its node
+pub trait Walk {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+}
+
+/// visits the children of the given expression with the given walker
+pub fn walk_expr<W: Walk + ?Sized>(walker: &mut W, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::LiteralString(_)
+        | ExprKind::LiteralNumber(_)
+        | ExprKind::LiteralTrue
+        | ExprKind::LiteralFalse
+        | ExprKind::LiteralNil => {}
+        ExprKind::Grouping { expression } => walker.visit_expr(expression),
+        ExprKind::Unary { expression, .. } => walker.visit_expr(expression),
+        ExprKind::Binary { left, right, .. } => {
+            walker.visit_expr(left);
+            walker.visit_expr(right);
+        }
+        ExprKind::List { elements } => {
+            for element in elements {
+                walker.visit_expr(element);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            walker.visit_expr(object);
+            walker.visit_expr(index);
+        }
+        ExprKind::Map { entries } => {
+            for (key, value) in entries {
+                walker.visit_expr(key);
+                walker.visit_expr(value);
+            }
+        }
+        ExprKind::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            walker.visit_expr(condition);
+            walker.visit_expr(then_branch);
+            walker.visit_expr(else_branch);
+        }
+    }
+}
+
+/// visits the children of the given statement with the given walker
+pub fn walk_stmt<W: Walk + ?Sized>(walker: &mut W, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Expression(expr) | StmtKind::Print(expr) => walker.visit_expr(expr),
+        StmtKind::Block(statements) => {
+            for statement in statements {
+                walker.visit_stmt(statement);
+            }
+        }
     }
 }
 
 pub struct ASTPrint;
-impl Visitor<Expr<'_>> for ASTPrint {
+impl Visitor<Expr> for ASTPrint {
     type Return = String;
 
-    fn visit(value: &Expr<'_>) -> Self::Return {
-        match value {
-            Expr::LiteralString(s) => format!("literal {}", s),
-            Expr::LiteralNumber(n) => format!("literal {}", n),
-            Expr::LiteralTrue => "literal true".to_string(),
-            Expr::LiteralFalse => "literal false".to_string(),
-            Expr::LiteralNil => "literal nil".to_string(),
-            Expr::Grouping { expression } => format!("grouping ( {} )", Self::visit(expression)),
-            Expr::Unary { prefix, expression } => {
-                format!("unary {} {}", prefix, Self::visit(expression))
-            }
-            Expr::Binary {
+    fn visit(&mut self, value: &Expr) -> Self::Return {
+        match &value.kind {
+            ExprKind::LiteralString(s) => format!("literal {}", s),
+            ExprKind::LiteralNumber(n) => format!("literal {}", n),
+            ExprKind::LiteralTrue => "literal true".to_string(),
+            ExprKind::LiteralFalse => "literal false".to_string(),
+            ExprKind::LiteralNil => "literal nil".to_string(),
+            ExprKind::Grouping { expression } => {
+                format!("grouping ( {} )", expression.accept(self))
+            }
+            ExprKind::Unary { prefix, expression } => {
+                format!("unary {} {}", prefix, expression.accept(self))
+            }
+            ExprKind::Binary {
                 left,
                 operator,
                 right,
             } => format!(
                 "binary {} {} {}",
-                Self::visit(left),
+                left.accept(self),
                 operator,
-                Self::visit(right)
+                right.accept(self)
             ),
+            ExprKind::List { elements } => {
+                let elements: Vec<String> = elements.iter().map(|e| e.accept(self)).collect();
+                format!("list [ {} ]", elements.join(", "))
+            }
+            ExprKind::Index { object, index } => {
+                format!("index {} [ {} ]", object.accept(self), index.accept(self))
+            }
+            ExprKind::Map { entries } => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key.accept(self), value.accept(self)))
+                    .collect();
+                format!("map {{ {} }}", entries.join(", "))
+            }
+            ExprKind::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!(
+                "conditional {} ? {} : {}",
+                condition.accept(self),
+                then_branch.accept(self),
+                else_branch.accept(self)
+            ),
+        }
+    }
+}
+
+impl Visitor<Stmt> for ASTPrint {
+    type Return = String;
+
+    fn visit(&mut self, value: &Stmt) -> Self::Return {
+        match &value.kind {
+            StmtKind::Expression(expr) => format!("expression {}", expr.accept(self)),
+            StmtKind::Print(expr) => format!("print {}", expr.accept(self)),
+            StmtKind::Block(statements) => {
+                let statements: Vec<String> = statements.iter().map(|s| s.accept(self)).collect();
+                format!("block {{ {} }}", statements.join("; "))
+            }
+        }
+    }
+}
+
+/// prints the tree as parenthesized s-expressions in the same format the
+/// book uses, e.g. `(* (- 55.5) (group 77))`, operators are printed with their
+/// lexeme, so the printer needs the source the tree was parsed from
+pub struct AstPrinter<'a> {
+    source: &'a [u8],
+}
+
+impl<'a> AstPrinter<'a> {
+    pub fn new(source: &'a [u8]) -> AstPrinter<'a> {
+        AstPrinter { source }
+    }
+
+    fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
+        let mut result = format!("({}", name);
+        for expr in exprs {
+            result.push(' ');
+            result.push_str(&expr.accept(self));
+        }
+        result.push(')');
+        result
+    }
+}
+
+impl Visitor<Expr> for AstPrinter<'_> {
+    type Return = String;
+
+    fn visit(&mut self, value: &Expr) -> Self::Return {
+        match &value.kind {
+            ExprKind::LiteralString(s) => s.clone(),
+            ExprKind::LiteralNumber(n) => n.to_string(),
+            ExprKind::LiteralTrue => "true".to_string(),
+            ExprKind::LiteralFalse => "false".to_string(),
+            ExprKind::LiteralNil => "nil".to_string(),
+            ExprKind::Grouping { expression } => self.parenthesize("group", &[expression]),
+            ExprKind::Unary { prefix, expression } => {
+                self.parenthesize(prefix.lexeme(self.source), &[expression])
+            }
+            ExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => self.parenthesize(operator.lexeme(self.source), &[left, right]),
+            ExprKind::List { elements } => {
+                let elements: Vec<&Expr> = elements.iter().collect();
+                self.parenthesize("list", &elements)
+            }
+            ExprKind::Index { object, index } => self.parenthesize("index", &[object, index]),
+            ExprKind::Map { entries } => {
+                let entries: Vec<&Expr> = entries
+                    .iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect();
+                self.parenthesize("map", &entries)
+            }
+            ExprKind::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.parenthesize("?:", &[condition, then_branch, else_branch]),
+        }
+    }
+}
+
+impl Visitor<Stmt> for AstPrinter<'_> {
+    type Return = String;
+
+    fn visit(&mut self, value: &Stmt) -> Self::Return {
+        match &value.kind {
+            StmtKind::Expression(expr) => self.parenthesize(";", &[expr]),
+            StmtKind::Print(expr) => self.parenthesize("print", &[expr]),
+            StmtKind::Block(statements) => {
+                let mut result = "(block".to_string();
+                for statement in statements {
+                    result.push(' ');
+                    result.push_str(&statement.accept(self));
+                }
+                result.push(')');
+                result
+            }
+        }
+    }
+}
+
+/// exports the tree as json, so external tools can consume the parse tree, every
+/// node is an object with a `type` and a `span`, plus the node fields
+pub struct JsonExporter<'a> {
+    source: &'a [u8],
+}
+
+impl<'a> JsonExporter<'a> {
+    pub fn new(source: &'a [u8]) -> JsonExporter<'a> {
+        JsonExporter { source }
+    }
+
+    fn node(&self, type_: &str, span: &Span, fields: &[(&str, String)]) -> String {
+        let mut result = format!(
+            "{{\"type\":{},\"span\":{{\"start\":{},\"end\":{},\"line\":{}}}",
+            json_string(type_),
+            span.start,
+            span.end,
+            span.line
+        );
+        for (name, value) in fields {
+            result.push_str(&format!(",{}:{}", json_string(name), value));
+        }
+        result.push('}');
+        result
+    }
+}
+
+impl Visitor<Expr> for JsonExporter<'_> {
+    type Return = String;
+
+    fn visit(&mut self, value: &Expr) -> Self::Return {
+        let span = &value.span;
+        match &value.kind {
+            ExprKind::LiteralString(s) => self.node("Literal", span, &[("value", json_string(s))]),
+            ExprKind::LiteralNumber(n) => self.node("Literal", span, &[("value", json_number(*n))]),
+            ExprKind::LiteralTrue => self.node("Literal", span, &[("value", "true".into())]),
+            ExprKind::LiteralFalse => self.node("Literal", span, &[("value", "false".into())]),
+            ExprKind::LiteralNil => self.node("Literal", span, &[("value", "null".into())]),
+            ExprKind::Grouping { expression } => {
+                let expression = expression.accept(self);
+                self.node("Grouping", span, &[("expression", expression)])
+            }
+            ExprKind::Unary { prefix, expression } => {
+                let expression = expression.accept(self);
+                let operator = json_string(prefix.lexeme(self.source));
+                self.node(
+                    "Unary",
+                    span,
+                    &[("operator", operator), ("expression", expression)],
+                )
+            }
+            ExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = left.accept(self);
+                let right = right.accept(self);
+                let operator = json_string(operator.lexeme(self.source));
+                self.node(
+                    "Binary",
+                    span,
+                    &[("left", left), ("operator", operator), ("right", right)],
+                )
+            }
+            ExprKind::List { elements } => {
+                let elements: Vec<String> = elements.iter().map(|e| e.accept(self)).collect();
+                let elements = format!("[{}]", elements.join(","));
+                self.node("List", span, &[("elements", elements)])
+            }
+            ExprKind::Index { object, index } => {
+                let object = object.accept(self);
+                let index = index.accept(self);
+                self.node("Index", span, &[("object", object), ("index", index)])
+            }
+            ExprKind::Map { entries } => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{{\"key\":{},\"value\":{}}}",
+                            key.accept(self),
+                            value.accept(self)
+                        )
+                    })
+                    .collect();
+                let entries = format!("[{}]", entries.join(","));
+                self.node("Map", span, &[("entries", entries)])
+            }
+            ExprKind::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = condition.accept(self);
+                let then_branch = then_branch.accept(self);
+                let else_branch = else_branch.accept(self);
+                self.node(
+                    "Conditional",
+                    span,
+                    &[
+                        ("condition", condition),
+                        ("then", then_branch),
+                        ("else", else_branch),
+                    ],
+                )
+            }
+        }
+    }
+}
+
+impl Visitor<Stmt> for JsonExporter<'_> {
+    type Return = String;
+
+    fn visit(&mut self, value: &Stmt) -> Self::Return {
+        let span = &value.span;
+        match &value.kind {
+            StmtKind::Expression(expr) => {
+                let expression = expr.accept(self);
+                self.node("Expression", span, &[("expression", expression)])
+            }
+            StmtKind::Print(expr) => {
+                let expression = expr.accept(self);
+                self.node("Print", span, &[("expression", expression)])
+            }
+            StmtKind::Block(statements) => {
+                let statements: Vec<String> = statements.iter().map(|s| s.accept(self)).collect();
+                let statements = format!("[{}]", statements.join(","));
+                self.node("Block", span, &[("statements", statements)])
+            }
         }
     }
 }
 
-pub enum Expr<'a> {
+/// exports the tree as a graphviz dot graph, every visit adds the node to the
+/// graph and returns its id, call `finish` to get the graph once the tree is visited
+pub struct DotExporter<'a> {
+    source: &'a [u8],
+    lines: Vec<String>,
+    next_id: usize,
+}
+
+impl<'a> DotExporter<'a> {
+    pub fn new(source: &'a [u8]) -> DotExporter<'a> {
+        DotExporter {
+            source,
+            lines: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// adds a node with the given label and edges to its children, returns the node id
+    fn node(&mut self, label: &str, children: &[(&str, usize)]) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.lines
+            .push(format!("    node{} [label=\"{}\"];", id, label));
+        for (edge, child) in children {
+            self.lines.push(format!(
+                "    node{} -> node{} [label=\"{}\"];",
+                id, child, edge
+            ));
+        }
+        id
+    }
+
+    /// returns the dot graph of all the visited nodes
+    pub fn finish(self) -> String {
+        format!("digraph ast {{\n{}\n}}", self.lines.join("\n"))
+    }
+}
+
+impl Visitor<Expr> for DotExporter<'_> {
+    type Return = usize;
+
+    fn visit(&mut self, value: &Expr) -> Self::Return {
+        match &value.kind {
+            ExprKind::LiteralString(s) => self.node(&format!("\"{}\"", s), &[]),
+            ExprKind::LiteralNumber(n) => self.node(&n.to_string(), &[]),
+            ExprKind::LiteralTrue => self.node("true", &[]),
+            ExprKind::LiteralFalse => self.node("false", &[]),
+            ExprKind::LiteralNil => self.node("nil", &[]),
+            ExprKind::Grouping { expression } => {
+                let expression = expression.accept(self);
+                self.node("group", &[("expression", expression)])
+            }
+            ExprKind::Unary { prefix, expression } => {
+                let expression = expression.accept(self);
+                self.node(prefix.lexeme(self.source), &[("expression", expression)])
+            }
+            ExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = left.accept(self);
+                let right = right.accept(self);
+                self.node(
+                    operator.lexeme(self.source),
+                    &[("left", left), ("right", right)],
+                )
+            }
+            ExprKind::List { elements } => {
+                let elements: Vec<usize> = elements.iter().map(|e| e.accept(self)).collect();
+                let edges: Vec<(&str, usize)> = elements.iter().map(|id| ("", *id)).collect();
+                self.node("list", &edges)
+            }
+            ExprKind::Index { object, index } => {
+                let object = object.accept(self);
+                let index = index.accept(self);
+                self.node("index", &[("object", object), ("index", index)])
+            }
+            ExprKind::Map { entries } => {
+                let mut edges = Vec::with_capacity(entries.len() * 2);
+                for (key, value) in entries {
+                    edges.push(("key", key.accept(self)));
+                    edges.push(("value", value.accept(self)));
+                }
+                self.node("map", &edges)
+            }
+            ExprKind::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = condition.accept(self);
+                let then_branch = then_branch.accept(self);
+                let else_branch = else_branch.accept(self);
+                self.node(
+                    "?:",
+                    &[
+                        ("condition", condition),
+                        ("then", then_branch),
+                        ("else", else_branch),
+                    ],
+                )
+            }
+        }
+    }
+}
+
+impl Visitor<Stmt> for DotExporter<'_> {
+    type Return = usize;
+
+    fn visit(&mut self, value: &Stmt) -> Self::Return {
+        match &value.kind {
+            StmtKind::Expression(expr) => {
+                let expression = expr.accept(self);
+                self.node("expression", &[("expression", expression)])
+            }
+            StmtKind::Print(expr) => {
+                let expression = expr.accept(self);
+                self.node("print", &[("expression", expression)])
+            }
+            StmtKind::Block(statements) => {
+                let statements: Vec<usize> = statements.iter().map(|s| s.accept(self)).collect();
+                let edges: Vec<(&str, usize)> = statements.iter().map(|id| ("", *id)).collect();
+                self.node("block", &edges)
+            }
+        }
+    }
+}
+
+/// an expression node, every node keeps the span of the source it was parsed from
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+pub enum ExprKind {
     LiteralString(String),
     LiteralNumber(f64),
     LiteralTrue,
     LiteralFalse,
     LiteralNil,
     Grouping {
-        expression: &'a Expr<'a>,
+        expression: Box<Expr>,
     },
     Unary {
         prefix: Token,
-        expression: &'a Expr<'a>,
+        expression: Box<Expr>,
     },
     Binary {
-        left: &'a Expr<'a>,
+        left: Box<Expr>,
         operator: Token,
-        right: &'a Expr<'a>,
+        right: Box<Expr>,
+    },
+    List {
+        elements: Vec<Expr>,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Map {
+        entries: Vec<(Expr, Expr)>,
+    },
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
     },
 }
 
-impl AcceptVisitor for Expr<'_> {}
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Expr { kind, span }
+    }
+
+    /// the span of a grouping includes the parentheses, which are not part
+    /// of the node, so it must be given by the caller
+    pub fn grouping(expression: Expr, span: Span) -> Expr {
+        Expr::new(
+            ExprKind::Grouping {
+                expression: Box::new(expression),
+            },
+            span,
+        )
+    }
+
+    pub fn unary(prefix: Token, expression: Expr) -> Expr {
+        let span = prefix.span().to(&expression.span);
+        Expr::new(
+            ExprKind::Unary {
+                prefix,
+                expression: Box::new(expression),
+            },
+            span,
+        )
+    }
+
+    pub fn binary(left: Expr, operator: Token, right: Expr) -> Expr {
+        let span = left.span.to(&right.span);
+        Expr::new(
+            ExprKind::Binary {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        )
+    }
+
+    /// the span of a list includes the brackets, so it must be given by the caller
+    pub fn list(elements: Vec<Expr>, span: Span) -> Expr {
+        Expr::new(ExprKind::List { elements }, span)
+    }
+
+    /// the span of an index expression ends with the closing bracket, so it
+    /// must be given by the caller
+    pub fn index(object: Expr, index: Expr, span: Span) -> Expr {
+        Expr::new(
+            ExprKind::Index {
+                object: Box::new(object),
+                index: Box::new(index),
+            },
+            span,
+        )
+    }
+
+    /// the span of a map includes the braces, so it must be given by the caller
+    pub fn map(entries: Vec<(Expr, Expr)>, span: Span) -> Expr {
+        Expr::new(ExprKind::Map { entries }, span)
+    }
+
+    /// `condition ? then_branch : else_branch`, only the taken branch is evaluated
+    pub fn conditional(condition: Expr, then_branch: Expr, else_branch: Expr) -> Expr {
+        let span = condition.span.to(&else_branch.span);
+        Expr::new(
+            ExprKind::Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            },
+            span,
+        )
+    }
+}
+
+impl AcceptVisitor for Expr {}
+
+/// a statement node, every node keeps the span of the source it was parsed from
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+pub enum StmtKind {
+    Expression(Expr),
+    Print(Expr),
+    Block(Vec<Stmt>),
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Stmt {
+        Stmt { kind, span }
+    }
+}
+
+impl AcceptVisitor for Stmt {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{Literal, TokenKind};
+
+    /// counts the literals and statements, relying on the default walk for the rest
+    #[derive(Default)]
+    struct Counter {
+        literals: usize,
+        statements: usize,
+    }
+
+    impl Walk for Counter {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if matches!(
+                expr.kind,
+                ExprKind::LiteralString(_)
+                    | ExprKind::LiteralNumber(_)
+                    | ExprKind::LiteralTrue
+                    | ExprKind::LiteralFalse
+                    | ExprKind::LiteralNil
+            ) {
+                self.literals += 1;
+            }
+            walk_expr(self, expr);
+        }
+
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            self.statements += 1;
+            walk_stmt(self, stmt);
+        }
+    }
+
+    fn span() -> Span {
+        Span::new(0, 0, 1)
+    }
+
+    fn literal(kind: ExprKind) -> Expr {
+        Expr::new(kind, span())
+    }
+
+    fn number(n: f64) -> Expr {
+        literal(ExprKind::LiteralNumber(n))
+    }
+
+    fn operator(kind: TokenKind) -> Token {
+        Token::new(kind, Literal::None, 1, 1, 0, 0)
+    }
+
+    #[test]
+    fn walk_visits_every_node() {
+        // { print {"a": [1, 2], [true][0]: nil ? false : (-3)}; 4 + 5; }
+        let map = Expr::map(
+            vec![
+                (
+                    literal(ExprKind::LiteralString("a".to_string())),
+                    Expr::list(vec![number(1.0), number(2.0)], span()),
+                ),
+                (
+                    Expr::index(
+                        Expr::list(vec![literal(ExprKind::LiteralTrue)], span()),
+                        number(0.0),
+                        span(),
+                    ),
+                    Expr::conditional(
+                        literal(ExprKind::LiteralNil),
+                        literal(ExprKind::LiteralFalse),
+                        Expr::grouping(
+                            Expr::unary(operator(TokenKind::Minus), number(3.0)),
+                            span(),
+                        ),
+                    ),
+                ),
+            ],
+            span(),
+        );
+        let sum = Expr::binary(number(4.0), operator(TokenKind::Plus), number(5.0));
+        let block = Stmt::new(
+            StmtKind::Block(vec![
+                Stmt::new(StmtKind::Print(map), span()),
+                Stmt::new(StmtKind::Expression(sum), span()),
+            ]),
+            span(),
+        );
+
+        let mut counter = Counter::default();
+        counter.visit_stmt(&block);
+        assert_eq!(counter.literals, 10);
+        assert_eq!(counter.statements, 3);
+    }
+
+    #[test]
+    fn json_has_no_infinity_or_nan() {
+        let source = b"";
+        let value = |n: f64| number(n).accept(&mut JsonExporter::new(source));
+        assert!(value(1.5).ends_with("\"value\":1.5}"));
+        assert!(value(f64::INFINITY).ends_with("\"value\":null}"));
+        assert!(value(f64::NAN).ends_with("\"value\":null}"));
+    }
+
+    #[test]
+    fn dot_graph_escapes_the_labels() {
+        // "a\"b\\" + 1
+        let source = br#""a\"b\\" + 1"#;
+        let expr = Expr::binary(
+            literal(ExprKind::LiteralString("a\"b\\".to_string())),
+            Token::new(TokenKind::Plus, Literal::None, 1, 10, 9, 10),
+            number(1.0),
+        );
+
+        let mut exporter = DotExporter::new(source);
+        expr.accept(&mut exporter);
+        assert_eq!(
+            exporter.finish(),
+            r#"digraph ast {
+    node0 [label="\"a\"b\\\""];
+    node1 [label="1"];
+    node2 [label="+"];
+    node2 -> node0 [label="left"];
+    node2 -> node1 [label="right"];
+}"#
+        );
+    }
+}
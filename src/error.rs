@@ -1,9 +1,96 @@
 use std::fmt;
+use std::io::{self, Write};
+
+use crate::render::Renderer;
 
 #[derive(Debug)]
 pub enum LoxErrorType {
     UnexpectedCharacter(char),
     UnterminatedString,
+    InvalidEscape(String),
+    UnterminatedComment,
+    InvalidUtf8,
+    UnterminatedInterpolation,
+}
+
+impl LoxErrorType {
+    /// the stable code of the diagnostic, codes are never reused once assigned,
+    /// `E1xxx` are scan errors, use `explain` for the long description of a code
+    pub fn code(&self) -> &'static str {
+        match self {
+            LoxErrorType::UnterminatedString => "E1001",
+            LoxErrorType::UnexpectedCharacter(_) => "E1002",
+            LoxErrorType::InvalidEscape(_) => "E1003",
+            LoxErrorType::UnterminatedComment => "E1004",
+            LoxErrorType::InvalidUtf8 => "E1005",
+            LoxErrorType::UnterminatedInterpolation => "E1006",
+        }
+    }
+}
+
+/// returns the long description of the given diagnostic code with an example,
+/// `None` if there is no such code
+pub fn explain(code: &str) -> Option<&'static str> {
+    let explanation = match code {
+        "E1001" => {
+            "A string literal was not terminated before the end of the script.
+
+Strings may span multiple lines, so a missing closing `\"` is only noticed
+at the end of the script, the error points at the opening `\"`.
+
+    print \"hello;
+
+Add the closing quote:
+
+    print \"hello\";"
+        }
+        "E1002" => {
+            "The scanner found a character that does not start any token.
+
+    var a = 1 @ 2;
+
+Only ascii letters, digits and `_` may appear in identifiers by default,
+other characters are only allowed inside strings and comments."
+        }
+        "E1003" => {
+            "A string contains an escape sequence lox does not know.
+
+    print \"bad \\q escape\";
+
+The supported escapes are `\\n`, `\\t`, `\\\"`, `\\\\`, `\\$` and `\\u{XXXX}` where
+`XXXX` is 1 to 6 hex digits of a valid unicode scalar value, to write a
+backslash use `\\\\`."
+        }
+        "E1004" => {
+            "A block comment was not closed before the end of the script.
+
+Block comments nest, so every `/*` needs its own `*/`.
+
+    /* outer
+      /* inner */
+    still open
+
+Close the outer comment with another `*/`."
+        }
+        "E1005" => {
+            "The script contains bytes that are not valid utf8.
+
+Scripts must be utf8 encoded, convert the file to utf8 with your editor
+or a tool like `iconv`."
+        }
+        "E1006" => {
+            "An interpolated expression in a string was not closed.
+
+    print \"unclosed ${a + 1;
+
+The expression after `${` ends with the matching `}`, after which the
+string continues:
+
+    print \"closed ${a + 1}\";"
+        }
+        _ => return None,
+    };
+    Some(explanation)
 }
 
 impl fmt::Display for LoxErrorType {
@@ -15,6 +102,37 @@ impl fmt::Display for LoxErrorType {
             LoxErrorType::UnterminatedString => {
                 write!(f, "String was not terminated.")
             }
+            LoxErrorType::InvalidEscape(escape) => {
+                write!(f, "Invalid escape sequence `{}`.", escape)
+            }
+            LoxErrorType::UnterminatedComment => {
+                write!(f, "Block comment was not terminated.")
+            }
+            LoxErrorType::InvalidUtf8 => {
+                write!(f, "Invalid utf8 sequence.")
+            }
+            LoxErrorType::UnterminatedInterpolation => {
+                write!(f, "String interpolation was not terminated.")
+            }
+        }
+    }
+}
+
+/// how bad a diagnostic is, only errors stop the script from running, notes
+/// add information to the diagnostic before them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Note => write!(f, "Note"),
         }
     }
 }
@@ -22,17 +140,187 @@ impl fmt::Display for LoxErrorType {
 #[derive(Debug)]
 pub struct LoxError {
     line: u32,
+    column: u32,
+    // the byte offsets of the source the diagnostic points at, `end` is exclusive
+    start: usize,
+    end: usize,
+    severity: Severity,
     type_: LoxErrorType,
 }
 
 impl LoxError {
-    pub fn new(line: u32, type_: LoxErrorType) -> LoxError {
-        LoxError { line, type_ }
+    pub fn new(line: u32, column: u32, start: usize, end: usize, type_: LoxErrorType) -> LoxError {
+        LoxError {
+            line,
+            column,
+            start,
+            end,
+            severity: Severity::Error,
+            type_,
+        }
+    }
+
+    pub fn warning(
+        line: u32,
+        column: u32,
+        start: usize,
+        end: usize,
+        type_: LoxErrorType,
+    ) -> LoxError {
+        LoxError {
+            severity: Severity::Warning,
+            ..LoxError::new(line, column, start, end, type_)
+        }
+    }
+
+    pub fn note(line: u32, column: u32, start: usize, end: usize, type_: LoxErrorType) -> LoxError {
+        LoxError {
+            severity: Severity::Note,
+            ..LoxError::new(line, column, start, end, type_)
+        }
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn kind(&self) -> &LoxErrorType {
+        &self.type_
     }
 }
 
 impl fmt::Display for LoxError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[line {}] Error: {}", self.line, self.type_)
+        write!(
+            f,
+            "[line {}:{}] {}: {}",
+            self.line, self.column, self.severity, self.type_
+        )
+    }
+}
+
+/// the sink every phase reports its diagnostics to, it renders them to the
+/// given output as they come and keeps count of the errors and warnings, with
+/// `deny_warnings` warnings are reported and counted as errors
+pub struct Diagnostics<'a> {
+    renderer: Renderer<'a>,
+    output: Box<dyn Write + 'a>,
+    deny_warnings: bool,
+    errors: usize,
+    warnings: usize,
+}
+
+impl<'a> Diagnostics<'a> {
+    /// the binary passes stderr as the output, embedders can pass any writer,
+    /// e.g. a `Vec<u8>` to collect the rendered diagnostics, the colors of the
+    /// renderer are not decided by the output, see `ColorChoice::Auto`
+    pub fn new(
+        renderer: Renderer<'a>,
+        deny_warnings: bool,
+        output: Box<dyn Write + 'a>,
+    ) -> Diagnostics<'a> {
+        Diagnostics {
+            renderer,
+            output,
+            deny_warnings,
+            errors: 0,
+            warnings: 0,
+        }
+    }
+
+    /// renders the diagnostic to the output, the diagnostic is counted even
+    /// when writing it fails
+    pub fn report(&mut self, mut error: LoxError) -> io::Result<()> {
+        if error.severity == Severity::Warning && self.deny_warnings {
+            error.severity = Severity::Error;
+        }
+
+        match error.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note => {}
+        }
+        self.output
+            .write_all(self.renderer.render(&error).as_bytes())
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.errors > 0
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::ColorChoice;
+
+    /// reports a warning and a note, returns if there was an error, the
+    /// number of warnings and the rendered diagnostics
+    fn report_warning_and_note(deny_warnings: bool) -> (bool, usize, String) {
+        let source = b"print 1;";
+        let mut output = Vec::new();
+        let mut diagnostics = Diagnostics::new(
+            Renderer::new("test.lox", source, ColorChoice::Never),
+            deny_warnings,
+            Box::new(&mut output),
+        );
+
+        diagnostics
+            .report(LoxError::warning(
+                1,
+                7,
+                6,
+                7,
+                LoxErrorType::UnterminatedString,
+            ))
+            .unwrap();
+        diagnostics
+            .report(LoxError::note(1, 1, 0, 5, LoxErrorType::UnterminatedString))
+            .unwrap();
+        let had_error = diagnostics.had_error();
+        let warnings = diagnostics.warnings();
+
+        drop(diagnostics);
+        (had_error, warnings, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn warnings_are_counted_but_are_not_errors() {
+        let (had_error, warnings, output) = report_warning_and_note(false);
+        assert!(!had_error);
+        assert_eq!(warnings, 1);
+        assert!(output.starts_with("warning[E1001]"));
+        assert!(output.contains("note[E1001]"));
+    }
+
+    #[test]
+    fn denied_warnings_are_reported_and_counted_as_errors() {
+        let (had_error, warnings, output) = report_warning_and_note(true);
+        assert!(had_error);
+        assert_eq!(warnings, 0);
+        assert!(output.starts_with("error[E1001]"));
+        // notes are never promoted
+        assert!(output.contains("note[E1001]"));
+        assert!(!output.contains("warning"));
     }
 }
@@ -0,0 +1,68 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// hands out a single shared `Rc<str>` for every distinct string, interning the
+/// same string twice returns the same allocation, so repeated strings are
+/// allocated once and interned strings can be compared with `Rc::ptr_eq`,
+/// cloning an interner returns a handle to the same strings, so the scanners
+/// of different sources (and later the interpreter) can share one interner
+#[derive(Clone, Default)]
+pub struct Interner {
+    strings: Rc<RefCell<HashSet<Rc<str>>>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    pub fn intern(&self, value: &str) -> Rc<str> {
+        let mut strings = self.strings.borrow_mut();
+        if let Some(interned) = strings.get(value) {
+            return Rc::clone(interned);
+        }
+
+        let interned: Rc<str> = Rc::from(value);
+        strings.insert(Rc::clone(&interned));
+        interned
+    }
+
+    /// the number of distinct strings interned so far
+    pub fn len(&self) -> usize {
+        self.strings.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.borrow().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_the_same_string_returns_the_same_allocation() {
+        let interner = Interner::new();
+        assert!(interner.is_empty());
+
+        let a = interner.intern("name");
+        let b = interner.intern(&String::from("name"));
+        let c = interner.intern("other");
+        assert!(Rc::ptr_eq(&a, &b));
+        assert!(!Rc::ptr_eq(&a, &c));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn clones_share_the_strings() {
+        let interner = Interner::new();
+        let shared = interner.clone();
+
+        let a = interner.intern("name");
+        let b = shared.intern("name");
+        assert!(Rc::ptr_eq(&a, &b));
+        assert_eq!(shared.len(), 1);
+    }
+}
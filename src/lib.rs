@@ -0,0 +1,67 @@
+//! a tree-walking interpreter for the lox language from
+//! [crafting interpreters](https://craftinginterpreters.com/), the `jlox`
+//! binary is a thin command line wrapper around this library
+//!
+//! there is no parser or interpreter yet, so sources can only be checked for
+//! static errors, nothing is evaluated and there is no `run` or `eval`
+//!
+//! ```
+//! use jlox::Lox;
+//!
+//! let lox = Lox::new();
+//! assert!(lox.check(b"print \"hello\";").is_ok());
+//!
+//! let errors = lox.check(b"print @;").unwrap_err();
+//! assert_eq!(errors[0].to_string(), "[line 1:7] Error: Unexpected character `@`.");
+//! ```
+//!
+//! the phases can also be used on their own, e.g. `scanner::Scanner` turns a
+//! source into tokens and the `ast` printers and exporters render a tree
+pub mod ast;
+pub mod error;
+pub mod interner;
+mod json;
+pub mod optimize;
+pub mod render;
+pub mod scanner;
+
+use error::LoxError;
+use scanner::Scanner;
+
+/// an interpreter instance, embedders create one and check sources with it
+#[derive(Default)]
+pub struct Lox {
+    unicode_identifiers: bool,
+}
+
+impl Lox {
+    pub fn new() -> Lox {
+        Lox::default()
+    }
+
+    /// allow identifiers to contain unicode letters and digits, see
+    /// `Scanner::unicode_identifiers`
+    pub fn unicode_identifiers(mut self, enabled: bool) -> Lox {
+        self.unicode_identifiers = enabled;
+        self
+    }
+
+    /// reports every static error of the given source without running it,
+    /// scanning is currently the only static phase, the parser and resolver
+    /// will report here once they exist
+    pub fn check(&self, source: &[u8]) -> Result<(), Vec<LoxError>> {
+        // the scan interns into its own interner, the tokens are dropped once
+        // the errors are collected, so nothing is kept between checks
+        let errors: Vec<LoxError> = Scanner::new(source)
+            .unicode_identifiers(self.unicode_identifiers)
+            .skip_trivia(true)
+            .filter_map(Result::err)
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
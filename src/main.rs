@@ -1,55 +1,299 @@
 use anyhow::{bail, Result};
-use ast::AcceptVisitor;
 use std::env;
 use std::fs;
+use std::io::{self, Read, Write};
 use std::path::PathBuf;
+use std::process;
 
-mod ast;
-mod error;
-mod scanner;
+use jlox::ast::{ASTPrint, AcceptVisitor, AstPrinter, DotExporter, Expr, ExprKind, JsonExporter};
+use jlox::error::{self, Diagnostics};
+use jlox::optimize;
+use jlox::render::{ColorChoice, ErrorFormat, Renderer};
+use jlox::scanner::{Literal, Scanner, Span, Token, TokenKind};
+use jlox::Lox;
 
-use ast::{ASTPrint, Expr};
-use scanner::{Scanner, Token, TokenKind};
+// exit codes follow jlox, which uses the BSD `sysexits.h` conventions
+const EXIT_USAGE: i32 = 64;
+const EXIT_DATA_ERROR: i32 = 65;
+
+const USAGE: &str =
+    "Usage: jlox check [--deny-warnings] [<scan options>] [<diagnostic options>] <script>
+       jlox --tokens [--include-trivia] [<scan options>] [<diagnostic options>] <script>
+       jlox [-O] [--dump-ast=tree|sexpr|json|dot]
+       jlox --explain <code>
+
+Scan options: --unicode-identifiers
+Diagnostic options: --color=always|never|auto --error-format=human|json";
+
+/// prints the usage and exits with the usage error exit code
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(EXIT_USAGE);
+}
 
 fn main() -> Result<()> {
-    let expr = Expr::Binary {
-        left: &Expr::Unary {
-            prefix: Token::new(TokenKind::Minus, "-".into(), "".into(), 1),
-            expression: &Expr::LiteralNumber(55.5),
-        },
-        operator: Token::new(TokenKind::Star, "*".into(), "".into(), 1),
-        right: &Expr::Grouping {
-            expression: &Expr::LiteralNumber(77.0),
-        },
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (flags, positional): (Vec<&str>, Vec<&str>) = args
+        .iter()
+        .map(String::as_str)
+        .partition(|arg| arg.starts_with('-') && *arg != "-");
+    let flag = |name: &str| flags.contains(&name);
+
+    let dump_ast_format = flags.iter().find_map(|arg| arg.strip_prefix("--dump-ast="));
+    let known_flag = |arg: &&str| {
+        matches!(
+            *arg,
+            "--tokens"
+                | "--include-trivia"
+                | "--deny-warnings"
+                | "--unicode-identifiers"
+                | "-O"
+                | "--explain"
+        ) || arg.starts_with("--dump-ast=")
+            || is_diagnostic_option(arg)
     };
+    if !flags.iter().all(known_flag) {
+        usage();
+    }
+
+    // diagnostics are colored when stderr is a terminal, unless `--color` says otherwise
+    let color = match flags.iter().find_map(|arg| arg.strip_prefix("--color=")) {
+        Some(value) => ColorChoice::parse(value).unwrap_or_else(|| usage()),
+        None => ColorChoice::Auto,
+    };
+    let error_format = match flags
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--error-format="))
+    {
+        Some(value) => ErrorFormat::parse(value).unwrap_or_else(|| usage()),
+        None => ErrorFormat::Human,
+    };
+    let options = DiagnosticOptions {
+        color,
+        format: error_format,
+    };
+
+    match positional.as_slice() {
+        // `check <path>` validates the script without running it, warnings
+        // fail the check too with `--deny-warnings`
+        ["check", path]
+            if flags.iter().all(|arg| {
+                matches!(*arg, "--deny-warnings" | "--unicode-identifiers")
+                    || is_diagnostic_option(arg)
+            }) =>
+        {
+            let lox = Lox::new().unicode_identifiers(flag("--unicode-identifiers"));
+            check(&lox, path, flag("--deny-warnings"), options)
+        }
+        // `--tokens <path>` only runs the scanner and prints the tokens, trivia
+        // tokens are skipped unless `--include-trivia` is given
+        [path]
+            if flag("--tokens")
+                && flags.iter().all(|arg| {
+                    matches!(
+                        *arg,
+                        "--tokens" | "--include-trivia" | "--unicode-identifiers"
+                    ) || is_diagnostic_option(arg)
+                }) =>
+        {
+            dump_tokens(
+                path,
+                flag("--include-trivia"),
+                flag("--unicode-identifiers"),
+                options,
+            )
+        }
+        // `--explain <code>` prints the long description of a diagnostic code
+        [code] if flags == ["--explain"] => explain(code),
+        // `-O` runs the optimization passes on the tree before it is printed
+        [] if flags
+            .iter()
+            .all(|arg| *arg == "-O" || arg.starts_with("--dump-ast=")) =>
+        {
+            dump_ast(dump_ast_format, flag("-O"))
+        }
+        _ => usage(),
+    }
+}
+
+fn is_diagnostic_option(arg: &str) -> bool {
+    arg.starts_with("--color=") || arg.starts_with("--error-format=")
+}
+
+/// how diagnostics are rendered, set with the diagnostic options
+#[derive(Clone, Copy)]
+struct DiagnosticOptions {
+    color: ColorChoice,
+    format: ErrorFormat,
+}
+
+impl DiagnosticOptions {
+    fn renderer<'a>(self, path: &'a str, content: &'a [u8]) -> Renderer<'a> {
+        Renderer::new(script_name(path), content, self.color).format(self.format)
+    }
+}
+
+/// the name diagnostics use for the script at the given path
+fn script_name(path: &str) -> &str {
+    if path == "-" {
+        "<stdin>"
+    } else {
+        path
+    }
+}
+
+/// reads the script at the given path, `-` reads the script from stdin
+fn read_script(path: &str) -> Result<Vec<u8>> {
+    if path == "-" {
+        let mut content = Vec::new();
+        io::stdin().read_to_end(&mut content)?;
+        return Ok(content);
+    }
 
-    println!("{}", expr.accept::<ASTPrint>());
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        bail!(format!("given path `{:?}` does not exists", path));
+    }
+    Ok(fs::read(path)?)
+}
+
+/// reports every static error and warning of the given script without executing it,
+/// exits with `EXIT_DATA_ERROR` if there were any errors, scanning is currently the only
+/// static phase, the parser and resolver will report here once they exist
+fn check(lox: &Lox, path: &str, deny_warnings: bool, options: DiagnosticOptions) -> Result<()> {
+    let content = read_script(path)?;
+    let mut diagnostics = Diagnostics::new(
+        options.renderer(path, &content),
+        deny_warnings,
+        Box::new(io::stderr()),
+    );
+
+    if let Err(errors) = lox.check(&content) {
+        for error in errors {
+            diagnostics.report(error)?;
+        }
+    }
+
+    if diagnostics.had_error() {
+        process::exit(EXIT_DATA_ERROR);
+    }
+    Ok(())
+}
+
+/// prints every token of the given script in a stable tabular format,
+/// one token per line with its position, kind, lexeme and literal
+fn dump_tokens(
+    path: &str,
+    include_trivia: bool,
+    unicode_identifiers: bool,
+    options: DiagnosticOptions,
+) -> Result<()> {
+    let content = read_script(path)?;
+    let mut diagnostics = Diagnostics::new(
+        options.renderer(path, &content),
+        false,
+        Box::new(io::stderr()),
+    );
+
+    let scanner = Scanner::new(&content)
+        .unicode_identifiers(unicode_identifiers)
+        .skip_trivia(!include_trivia);
+    match write_tokens(
+        &mut io::stdout().lock(),
+        scanner,
+        &content,
+        &mut diagnostics,
+    ) {
+        // the dump is meant to be piped, a reader that stops early (e.g. `head`)
+        // closes the pipe, which is not an error
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+        result => result?,
+    }
+
+    if diagnostics.had_error() {
+        process::exit(EXIT_DATA_ERROR);
+    }
+    Ok(())
+}
+
+/// writes the tokens of the scanner to the given output, the scan errors
+/// are reported to the diagnostics
+fn write_tokens(
+    out: &mut impl Write,
+    scanner: Scanner,
+    content: &[u8],
+    diagnostics: &mut Diagnostics,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:<10} {:<14} {:<24} LITERAL",
+        "POSITION", "KIND", "LEXEME"
+    )?;
+    for token in scanner {
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                diagnostics.report(e)?;
+                continue;
+            }
+        };
+
+        let line = format!(
+            "{:<10} {:<14} {:<24} {}",
+            format!("{}:{}", token.line(), token.column()),
+            token.kind().to_string(),
+            format!("{:?}", token.lexeme(content)),
+            token.literal()
+        );
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// prints the long description of the given diagnostic code, exits with
+/// the usage exit code if there is no such code
+fn explain(code: &str) -> Result<()> {
+    match error::explain(code) {
+        Some(explanation) => println!("{}", explanation),
+        None => {
+            eprintln!("error: no diagnostic has the code `{}`", code);
+            process::exit(EXIT_USAGE);
+        }
+    }
+    Ok(())
+}
+
+/// prints an example tree with the given format, `sexpr` is the same format
+/// the book uses, `json` is for external tools and `dot` is a graphviz graph
+fn dump_ast(format: Option<&str>, optimize: bool) -> Result<()> {
+    let source = b"-55.5 * (77)";
+    let expr = Expr::binary(
+        Expr::unary(
+            Token::new(TokenKind::Minus, Literal::None, 1, 1, 0, 1),
+            Expr::new(ExprKind::LiteralNumber(55.5), Span::new(1, 5, 1)),
+        ),
+        Token::new(TokenKind::Star, Literal::None, 1, 7, 6, 7),
+        Expr::grouping(
+            Expr::new(ExprKind::LiteralNumber(77.0), Span::new(9, 11, 1)),
+            Span::new(8, 12, 1),
+        ),
+    );
+    let expr = if optimize {
+        optimize::fold_expr(expr)
+    } else {
+        expr
+    };
 
+    match format {
+        None | Some("tree") => println!("{}", expr.accept(&mut ASTPrint)),
+        Some("sexpr") => println!("{}", expr.accept(&mut AstPrinter::new(source))),
+        Some("json") => println!("{}", expr.accept(&mut JsonExporter::new(source))),
+        Some("dot") => {
+            let mut exporter = DotExporter::new(source);
+            expr.accept(&mut exporter);
+            println!("{}", exporter.finish());
+        }
+        Some(_) => usage(),
+    }
     Ok(())
-    // match env::args().nth(1) {
-    //     Some(p) => {
-    //         let path = PathBuf::from(p);
-    //         if !path.exists() {
-    //             bail!(format!("given path `{:?}` does not exists", path));
-    //         }
-
-    //         let scanner = Scanner::new(fs::read(path).unwrap());
-
-    //         for token in scanner {
-    //             if let Err(e) = token {
-    //                 bail!(format!("{}", e));
-    //             }
-
-    //             let token = token.unwrap();
-    //             match token.kind() {
-    //                 TokenKind::WhiteSpace | TokenKind::NewLine | TokenKind::Comment => {}
-    //                 _ => {
-    //                     println!("{}", token);
-    //                 }
-    //             }
-    //         }
-    //         Ok(())
-    //     }
-    //     None => Ok(()),
-    // }
 }
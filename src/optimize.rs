@@ -0,0 +1,298 @@
+//! optimization passes that run on the tree before it is interpreted, every
+//! pass takes the tree by value and returns the rewritten tree
+use crate::ast::{Expr, ExprKind, Stmt, StmtKind};
+use crate::scanner::TokenKind;
+
+/// folds every constant expression in the statement, see `fold_expr`
+pub fn fold_stmt(stmt: Stmt) -> Stmt {
+    let kind = match stmt.kind {
+        StmtKind::Expression(expr) => StmtKind::Expression(fold_expr(expr)),
+        StmtKind::Print(expr) => StmtKind::Print(fold_expr(expr)),
+        StmtKind::Block(statements) => {
+            StmtKind::Block(statements.into_iter().map(fold_stmt).collect())
+        }
+    };
+    Stmt::new(kind, stmt.span)
+}
+
+/// folds constant arithmetic, comparison, equality, negation and string
+/// concatenation into a single literal, e.g. `2 * 3 + [x]` becomes `6 + [x]`,
+/// a folded literal keeps the span of the whole expression it replaced,
+/// operations that would fail at runtime (e.g. `-"a"`) or whose result is not
+/// decided yet (division by zero) are left for the interpreter
+pub fn fold_expr(expr: Expr) -> Expr {
+    let span = expr.span;
+    let kind = match expr.kind {
+        ExprKind::Grouping { expression } => {
+            let expression = fold_expr(*expression);
+            if is_literal(&expression.kind) {
+                expression.kind
+            } else {
+                ExprKind::Grouping {
+                    expression: Box::new(expression),
+                }
+            }
+        }
+        ExprKind::Unary { prefix, expression } => {
+            let expression = fold_expr(*expression);
+            match (prefix.kind(), &expression.kind) {
+                (TokenKind::Minus, ExprKind::LiteralNumber(n)) => ExprKind::LiteralNumber(-n),
+                (TokenKind::Bang, kind) if is_literal(kind) => boolean(!is_truthy(kind)),
+                _ => ExprKind::Unary {
+                    prefix,
+                    expression: Box::new(expression),
+                },
+            }
+        }
+        ExprKind::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = fold_expr(*left);
+            let right = fold_expr(*right);
+            match fold_binary(operator.kind(), &left.kind, &right.kind) {
+                Some(kind) => kind,
+                None => ExprKind::Binary {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                },
+            }
+        }
+        ExprKind::List { elements } => ExprKind::List {
+            elements: elements.into_iter().map(fold_expr).collect(),
+        },
+        ExprKind::Index { object, index } => ExprKind::Index {
+            object: Box::new(fold_expr(*object)),
+            index: Box::new(fold_expr(*index)),
+        },
+        ExprKind::Map { entries } => ExprKind::Map {
+            entries: entries
+                .into_iter()
+                .map(|(key, value)| (fold_expr(key), fold_expr(value)))
+                .collect(),
+        },
+        ExprKind::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let condition = fold_expr(*condition);
+            let then_branch = fold_expr(*then_branch);
+            let else_branch = fold_expr(*else_branch);
+            // only the taken branch would be evaluated, so the other one can be dropped
+            match &condition.kind {
+                kind if is_literal(kind) && is_truthy(kind) => then_branch.kind,
+                kind if is_literal(kind) => else_branch.kind,
+                _ => ExprKind::Conditional {
+                    condition: Box::new(condition),
+                    then_branch: Box::new(then_branch),
+                    else_branch: Box::new(else_branch),
+                },
+            }
+        }
+        kind => kind,
+    };
+    Expr::new(kind, span)
+}
+
+fn fold_binary(operator: TokenKind, left: &ExprKind, right: &ExprKind) -> Option<ExprKind> {
+    use ExprKind::{LiteralNumber, LiteralString};
+
+    let kind = match (operator, left, right) {
+        (TokenKind::Plus, LiteralNumber(a), LiteralNumber(b)) => LiteralNumber(a + b),
+        (TokenKind::Plus, LiteralString(a), LiteralString(b)) => LiteralString(format!("{a}{b}")),
+        (TokenKind::Minus, LiteralNumber(a), LiteralNumber(b)) => LiteralNumber(a - b),
+        (TokenKind::Star, LiteralNumber(a), LiteralNumber(b)) => LiteralNumber(a * b),
+        (TokenKind::Slash, LiteralNumber(a), LiteralNumber(b)) if *b != 0.0 => LiteralNumber(a / b),
+        (TokenKind::Greater, LiteralNumber(a), LiteralNumber(b)) => boolean(a > b),
+        (TokenKind::GreaterEqual, LiteralNumber(a), LiteralNumber(b)) => boolean(a >= b),
+        (TokenKind::Less, LiteralNumber(a), LiteralNumber(b)) => boolean(a < b),
+        (TokenKind::LessEqual, LiteralNumber(a), LiteralNumber(b)) => boolean(a <= b),
+        (TokenKind::EqualEqual, left, right) => boolean(literals_equal(left, right)?),
+        (TokenKind::BangEqual, left, right) => boolean(!literals_equal(left, right)?),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn is_literal(kind: &ExprKind) -> bool {
+    matches!(
+        kind,
+        ExprKind::LiteralString(_)
+            | ExprKind::LiteralNumber(_)
+            | ExprKind::LiteralTrue
+            | ExprKind::LiteralFalse
+            | ExprKind::LiteralNil
+    )
+}
+
+/// `nil` and `false` are falsey, every other value is truthy
+fn is_truthy(kind: &ExprKind) -> bool {
+    !matches!(kind, ExprKind::LiteralNil | ExprKind::LiteralFalse)
+}
+
+/// lox equality of two literals, values of different types are never
+/// equal, returns `None` if either side is not a literal
+fn literals_equal(left: &ExprKind, right: &ExprKind) -> Option<bool> {
+    if !is_literal(left) || !is_literal(right) {
+        return None;
+    }
+
+    let equal = match (left, right) {
+        (ExprKind::LiteralString(a), ExprKind::LiteralString(b)) => a == b,
+        (ExprKind::LiteralNumber(a), ExprKind::LiteralNumber(b)) => a == b,
+        (ExprKind::LiteralTrue, ExprKind::LiteralTrue)
+        | (ExprKind::LiteralFalse, ExprKind::LiteralFalse)
+        | (ExprKind::LiteralNil, ExprKind::LiteralNil) => true,
+        _ => false,
+    };
+    Some(equal)
+}
+
+fn boolean(value: bool) -> ExprKind {
+    if value {
+        ExprKind::LiteralTrue
+    } else {
+        ExprKind::LiteralFalse
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{AcceptVisitor, AstPrinter};
+    use crate::scanner::{Literal, Span, Token};
+
+    // every test tree is built over this source so the operators print with
+    // their lexeme, each operator is found by the first match of its lexeme
+    const SOURCE: &[u8] = b"+ - * / ! == != < >";
+
+    fn operator(kind: TokenKind, lexeme: &str) -> Token {
+        let start = std::str::from_utf8(SOURCE).unwrap().find(lexeme).unwrap();
+        let end = start + lexeme.len();
+        Token::new(kind, Literal::None, 1, start as u32 + 1, start, end)
+    }
+
+    fn literal(kind: ExprKind) -> Expr {
+        Expr::new(kind, Span::new(0, 0, 1))
+    }
+
+    fn number(n: f64) -> Expr {
+        literal(ExprKind::LiteralNumber(n))
+    }
+
+    fn string(s: &str) -> Expr {
+        literal(ExprKind::LiteralString(s.to_string()))
+    }
+
+    fn binary(left: Expr, kind: TokenKind, lexeme: &str, right: Expr) -> Expr {
+        Expr::binary(left, operator(kind, lexeme), right)
+    }
+
+    /// a list is never constant, so it stands in for a variable
+    fn opaque() -> Expr {
+        Expr::list(vec![], Span::new(0, 0, 1))
+    }
+
+    fn folded(expr: Expr) -> String {
+        fold_expr(expr).accept(&mut AstPrinter::new(SOURCE))
+    }
+
+    #[test]
+    fn folds_arithmetic() {
+        let expr = binary(
+            binary(number(2.0), TokenKind::Star, "*", number(3.0)),
+            TokenKind::Plus,
+            "+",
+            opaque(),
+        );
+        assert_eq!(folded(expr), "(+ 6 (list))");
+
+        let expr = binary(
+            Expr::grouping(
+                binary(number(1.0), TokenKind::Minus, "-", number(4.0)),
+                Span::new(0, 0, 1),
+            ),
+            TokenKind::Slash,
+            "/",
+            number(2.0),
+        );
+        assert_eq!(folded(expr), "-1.5");
+    }
+
+    #[test]
+    fn folds_negation() {
+        let expr = Expr::unary(operator(TokenKind::Minus, "-"), number(5.0));
+        assert_eq!(folded(expr), "-5");
+
+        let expr = Expr::unary(
+            operator(TokenKind::Bang, "!"),
+            literal(ExprKind::LiteralNil),
+        );
+        assert_eq!(folded(expr), "true");
+    }
+
+    #[test]
+    fn folds_comparison_and_equality() {
+        let expr = binary(number(1.0), TokenKind::Less, "<", number(2.0));
+        assert_eq!(folded(expr), "true");
+
+        let expr = binary(string("a"), TokenKind::EqualEqual, "==", number(1.0));
+        assert_eq!(folded(expr), "false");
+
+        let expr = binary(
+            literal(ExprKind::LiteralNil),
+            TokenKind::BangEqual,
+            "!=",
+            literal(ExprKind::LiteralNil),
+        );
+        assert_eq!(folded(expr), "false");
+    }
+
+    #[test]
+    fn folds_string_concatenation() {
+        let expr = binary(
+            binary(string("foo"), TokenKind::Plus, "+", string("bar")),
+            TokenKind::Plus,
+            "+",
+            string("baz"),
+        );
+        assert_eq!(folded(expr), "foobarbaz");
+    }
+
+    #[test]
+    fn folds_conditional_with_constant_condition() {
+        let expr = Expr::conditional(
+            binary(number(1.0), TokenKind::Greater, ">", number(2.0)),
+            opaque(),
+            number(3.0),
+        );
+        assert_eq!(folded(expr), "3");
+    }
+
+    #[test]
+    fn keeps_runtime_errors_and_division_by_zero() {
+        let expr = binary(number(1.0), TokenKind::Slash, "/", number(0.0));
+        assert_eq!(folded(expr), "(/ 1 0)");
+
+        let expr = binary(string("a"), TokenKind::Plus, "+", number(1.0));
+        assert_eq!(folded(expr), "(+ a 1)");
+
+        let expr = Expr::unary(operator(TokenKind::Minus, "-"), string("a"));
+        assert_eq!(folded(expr), "(- a)");
+    }
+
+    #[test]
+    fn folds_inside_statements() {
+        let stmt = Stmt::new(
+            StmtKind::Print(binary(number(2.0), TokenKind::Star, "*", number(4.0))),
+            Span::new(0, 0, 1),
+        );
+        assert_eq!(
+            fold_stmt(stmt).accept(&mut AstPrinter::new(SOURCE)),
+            "(print 8)"
+        );
+    }
+}
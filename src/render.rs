@@ -0,0 +1,226 @@
+//! renders diagnostics for people, with a header line, the location and the
+//! source line the diagnostic points at, e.g.
+//!
+//! ```text
+//! error[E1002]: Unexpected character `@`.
+//!  --> script.lox:2:11
+//!   |
+//! 2 | var b = a @ 2;
+//!   |           ^
+//! ```
+//!
+//! or as one json object per diagnostic for editors and other tools
+use std::env;
+use std::io::{self, IsTerminal};
+
+use crate::error::{LoxError, Severity};
+use crate::json::json_string;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const BOLD_GREEN: &str = "\x1b[1;32m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+
+/// when the renderer uses colors, `Auto` uses colors only when stderr is a
+/// terminal and the `NO_COLOR` environment variable is not set, it always
+/// looks at stderr, so embedders rendering to anything else (e.g. collecting
+/// the diagnostics into a buffer) should pass `Never` or `Always`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    Auto,
+}
+
+impl ColorChoice {
+    /// parses the value of the `--color` flag
+    pub fn parse(value: &str) -> Option<ColorChoice> {
+        match value {
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            "auto" => Some(ColorChoice::Auto),
+            _ => None,
+        }
+    }
+
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
+
+/// how the diagnostics are rendered, `Human` is the rendering with the source
+/// line, `Json` is a single line json object per diagnostic
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl ErrorFormat {
+    /// parses the value of the `--error-format` flag
+    pub fn parse(value: &str) -> Option<ErrorFormat> {
+        match value {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// renders the diagnostics of a single source, `path` is only used to
+/// point at the source in the location line
+pub struct Renderer<'a> {
+    path: &'a str,
+    source: &'a [u8],
+    color: bool,
+    format: ErrorFormat,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(path: &'a str, source: &'a [u8], color: ColorChoice) -> Renderer<'a> {
+        Renderer {
+            path,
+            source,
+            color: color.enabled(),
+            format: ErrorFormat::Human,
+        }
+    }
+
+    /// renders the diagnostics in the given format, by default `ErrorFormat::Human`
+    pub fn format(mut self, format: ErrorFormat) -> Renderer<'a> {
+        self.format = format;
+        self
+    }
+
+    /// wraps the text with the given ansi style, when colors are enabled
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn render(&self, error: &LoxError) -> String {
+        match self.format {
+            ErrorFormat::Human => self.render_human(error),
+            ErrorFormat::Json => self.render_json(error),
+        }
+    }
+
+    /// renders the diagnostic as a json object on a single line, colors are
+    /// never used, `notes` is kept for the notes related diagnostics will have
+    fn render_json(&self, error: &LoxError) -> String {
+        let severity = match error.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        format!(
+            "{{\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\
+             \"span\":{{\"start\":{},\"end\":{},\"line\":{},\"column\":{}}},\"notes\":[]}}\n",
+            json_string(error.kind().code()),
+            json_string(severity),
+            json_string(&error.kind().to_string()),
+            json_string(self.path),
+            error.start(),
+            error.end(),
+            error.line(),
+            error.column()
+        )
+    }
+
+    fn render_human(&self, error: &LoxError) -> String {
+        let (name, style) = match error.severity() {
+            Severity::Error => ("error", BOLD_RED),
+            Severity::Warning => ("warning", BOLD_YELLOW),
+            Severity::Note => ("note", BOLD_GREEN),
+        };
+
+        let mut result = format!(
+            "{}{}\n",
+            self.paint(style, &format!("{}[{}]", name, error.kind().code())),
+            self.paint(BOLD, &format!(": {}", error.kind()))
+        );
+
+        let line = error.line().to_string();
+        let gutter = " ".repeat(line.len());
+        result.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            self.paint(BOLD_BLUE, "-->"),
+            self.path,
+            error.line(),
+            error.column()
+        ));
+
+        // the line may be missing when the diagnostic points past the source
+        if let Some(text) = self.source_line(error.line()) {
+            // the caret is indented with the same characters as the line, so
+            // tabs before the column line up the same way in the terminal
+            let indent: String = text
+                .chars()
+                .take(error.column().saturating_sub(1) as usize)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            let bar = self.paint(BOLD_BLUE, "|");
+            result.push_str(&format!("{} {}\n", gutter, bar));
+            result.push_str(&format!(
+                "{} {} {}\n",
+                self.paint(BOLD_BLUE, &line),
+                bar,
+                text
+            ));
+            result.push_str(&format!(
+                "{} {} {}{}\n",
+                gutter,
+                bar,
+                indent,
+                self.paint(style, "^")
+            ));
+        }
+
+        // an empty line separates the diagnostics from each other
+        result.push('\n');
+        result
+    }
+
+    /// the text of the given 1-based line, without the line ending
+    fn source_line(&self, line: u32) -> Option<String> {
+        let text = self
+            .source
+            .split(|&byte| byte == b'\n')
+            .nth(line.checked_sub(1)? as usize)?;
+        let text = String::from_utf8_lossy(text);
+        Some(text.trim_end_matches('\r').to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn json_diagnostic() {
+        let source = b"print 1;\nprint \"a \\q\";";
+        let error = Scanner::new(source).find_map(Result::err).unwrap();
+
+        // the json format is parsed by editors and CI, so the whole line is pinned
+        let renderer =
+            Renderer::new("dir/\"a\".lox", source, ColorChoice::Always).format(ErrorFormat::Json);
+        assert_eq!(
+            renderer.render(&error),
+            "{\"code\":\"E1003\",\"severity\":\"error\",\
+             \"message\":\"Invalid escape sequence `\\\\q`.\",\"file\":\"dir/\\\"a\\\".lox\",\
+             \"span\":{\"start\":18,\"end\":20,\"line\":2,\"column\":10},\"notes\":[]}\n"
+        );
+    }
+}
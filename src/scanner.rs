@@ -1,40 +1,62 @@
 use phf::phf_map;
+use std::borrow::Cow;
 use std::fmt;
+use std::rc::Rc;
 
 use super::error::{LoxError, LoxErrorType};
+use super::interner::Interner;
 
 static KEYWORDS: phf::Map<&'static str, TokenKind> = phf_map!(
     "and" => TokenKind::And,
+    "break" => TokenKind::Break,
+    "case" => TokenKind::Case,
+    "catch" => TokenKind::Catch,
     "class" => TokenKind::Class,
+    "continue" => TokenKind::Continue,
+    "default" => TokenKind::Default,
     "else" => TokenKind::Else,
+    "false" => TokenKind::False,
+    "finally" => TokenKind::Finally,
     "func" => TokenKind::Func,
     "for" => TokenKind::For,
     "if" => TokenKind::If,
+    "import" => TokenKind::Import,
     "nil" => TokenKind::Nil,
     "or" => TokenKind::Or,
     "print" => TokenKind::Print,
     "return" => TokenKind::Return,
     "super" => TokenKind::Super,
+    "switch" => TokenKind::Switch,
     "this" => TokenKind::This,
+    "throw" => TokenKind::Throw,
     "true" => TokenKind::True,
+    "try" => TokenKind::Try,
     "var" => TokenKind::Var,
     "while" => TokenKind::While
 );
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TokenKind {
     // single character tokens
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
+    Question,
     Dot,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
 
     // One or two character tokens
     Bang,
@@ -45,27 +67,46 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    StarStar,
+    LessLess,
+    GreaterGreater,
 
     // Literals
     Identifier,
     String,
+    // a string segment followed by an interpolated expression `${`
+    Interpolation,
     Number,
 
     // Keywords
     And,
+    Break,
+    Case,
+    Catch,
     Class,
+    Continue,
+    Default,
     Else,
     False,
+    Finally,
     Func,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Switch,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -73,117 +114,16 @@ pub enum TokenKind {
     Comment,
     NewLine,
     WhiteSpace,
+    Eof,
 }
 
 impl TokenKind {
-    /// function returns the first found token from given utf8 bytes slice, if couldn't find
-    /// any token, then return an error, when token is found, return the matching token type and
-    /// the length of the matching token
-    pub fn from_utf8(value: &[u8]) -> Result<(Self, usize), LoxErrorType> {
-        match value[0].into() {
-            '\r' | '\t' | ' ' => {
-                let size = value[1..]
-                    .iter()
-                    .take_while(|c| matches!(c, b'\r' | b'\t' | b' '))
-                    .count()
-                    + 1;
-                Ok((TokenKind::WhiteSpace, size))
-            }
-            '\n' => Ok((TokenKind::NewLine, 1)),
-            '(' => Ok((TokenKind::LeftParen, 1)),
-            ')' => Ok((TokenKind::RightParen, 1)),
-            '{' => Ok((TokenKind::LeftBrace, 1)),
-            '}' => Ok((TokenKind::RightBrace, 1)),
-            ',' => Ok((TokenKind::Comma, 1)),
-            '.' => Ok((TokenKind::Dot, 1)),
-            '-' => Ok((TokenKind::Minus, 1)),
-            '+' => Ok((TokenKind::Plus, 1)),
-            ';' => Ok((TokenKind::Semicolon, 1)),
-            '*' => Ok((TokenKind::Star, 1)),
-            '=' => {
-                if value[1] == b'=' {
-                    Ok((TokenKind::EqualEqual, 2))
-                } else {
-                    Ok((TokenKind::Equal, 1))
-                }
-            }
-            '>' => {
-                if value[1] == b'=' {
-                    Ok((TokenKind::GreaterEqual, 2))
-                } else {
-                    Ok((TokenKind::Greater, 1))
-                }
-            }
-            '<' => {
-                if value[1] == b'=' {
-                    Ok((TokenKind::LessEqual, 2))
-                } else {
-                    Ok((TokenKind::Less, 1))
-                }
-            }
-            '!' => {
-                if value[1] == b'=' {
-                    Ok((TokenKind::BangEqual, 2))
-                } else {
-                    Ok((TokenKind::Bang, 1))
-                }
-            }
-            '/' => {
-                if value[1] == b'/' {
-                    // we add 2 because we started from index 2, we know that
-                    // the first 2 chars are `//`
-                    let size = value[2..].iter().take_while(|c| **c != b'\n').count() + 2;
-                    return Ok((TokenKind::Comment, size));
-                } else {
-                    Ok((TokenKind::Slash, 1))
-                }
-            }
-            '"' => {
-                for (i, byte) in value[1..].iter().enumerate() {
-                    if *byte == b'"' {
-                        return Ok((TokenKind::String, i + 2));
-                    }
-                }
-                Err(LoxErrorType::UnterminatedString)
-            }
-            '0'..'9' => {
-                let mut post_dot = false;
-                let mut size = 0usize;
-
-                for byte in value[1..].iter() {
-                    // look for numbers and dot floating points
-                    if *byte == b'.' {
-                        // if current char is a `.` (floating point) then we check
-                        // if we are already `post_dot` because a floating point cannot
-                        // have multiple dots
-                        if post_dot {
-                            break;
-                        }
-                        post_dot = true;
-                    } else if !byte.is_ascii_digit() {
-                        break;
-                    }
-                    size += 1;
-                }
-                Ok((TokenKind::Number, size))
-            }
-            'a'..'z' | 'A'..'Z' | '_' => {
-                let identifier = String::from_utf8(
-                    value
-                        .iter()
-                        .take_while(|c| matches!(c, b'a'..b'z' | b'A'..b'Z' | b'_'))
-                        .map(|c| *c)
-                        .collect(),
-                )
-                .unwrap();
-
-                match KEYWORDS.get(&identifier) {
-                    Some(t) => Ok(((*t).clone(), identifier.len())),
-                    None => Ok((TokenKind::Identifier, identifier.len())),
-                }
-            }
-            c => Err(LoxErrorType::UnexpectedCharacter(c)),
-        }
+    /// whitespace, newlines and comments, tokens that do not affect the program
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::WhiteSpace | TokenKind::NewLine | TokenKind::Comment
+        )
     }
 }
 
@@ -194,13 +134,21 @@ impl fmt::Display for TokenKind {
             TokenKind::RightParen => write!(f, "RightParen"),
             TokenKind::LeftBrace => write!(f, "LeftBrace"),
             TokenKind::RightBrace => write!(f, "RightBrace"),
-            TokenKind::Comma => write!(f, "Comman"),
+            TokenKind::LeftBracket => write!(f, "LeftBracket"),
+            TokenKind::RightBracket => write!(f, "RightBracket"),
+            TokenKind::Comma => write!(f, "Comma"),
+            TokenKind::Colon => write!(f, "Colon"),
+            TokenKind::Question => write!(f, "Question"),
             TokenKind::Dot => write!(f, "Dot"),
             TokenKind::Minus => write!(f, "Minus"),
             TokenKind::Plus => write!(f, "Plus"),
             TokenKind::Semicolon => write!(f, "Semicolon"),
             TokenKind::Slash => write!(f, "Slash"),
             TokenKind::Star => write!(f, "Star"),
+            TokenKind::Percent => write!(f, "Percent"),
+            TokenKind::Ampersand => write!(f, "Ampersand"),
+            TokenKind::Pipe => write!(f, "Pipe"),
+            TokenKind::Caret => write!(f, "Caret"),
             TokenKind::Bang => write!(f, "Bang"),
             TokenKind::BangEqual => write!(f, "BangEqual"),
             TokenKind::Equal => write!(f, "Equal"),
@@ -209,118 +157,1133 @@ impl fmt::Display for TokenKind {
             TokenKind::GreaterEqual => write!(f, "GreaterEqual"),
             TokenKind::Less => write!(f, "Less"),
             TokenKind::LessEqual => write!(f, "LessEqual"),
+            TokenKind::PlusEqual => write!(f, "PlusEqual"),
+            TokenKind::MinusEqual => write!(f, "MinusEqual"),
+            TokenKind::StarEqual => write!(f, "StarEqual"),
+            TokenKind::SlashEqual => write!(f, "SlashEqual"),
+            TokenKind::StarStar => write!(f, "StarStar"),
+            TokenKind::LessLess => write!(f, "LessLess"),
+            TokenKind::GreaterGreater => write!(f, "GreaterGreater"),
             TokenKind::Identifier => write!(f, "Identifier"),
             TokenKind::String => write!(f, "String"),
+            TokenKind::Interpolation => write!(f, "Interpolation"),
             TokenKind::Number => write!(f, "Number"),
             TokenKind::And => write!(f, "And"),
+            TokenKind::Break => write!(f, "Break"),
+            TokenKind::Case => write!(f, "Case"),
+            TokenKind::Catch => write!(f, "Catch"),
             TokenKind::Class => write!(f, "Class"),
+            TokenKind::Continue => write!(f, "Continue"),
+            TokenKind::Default => write!(f, "Default"),
             TokenKind::Else => write!(f, "Else"),
             TokenKind::False => write!(f, "False"),
+            TokenKind::Finally => write!(f, "Finally"),
             TokenKind::Func => write!(f, "Func"),
             TokenKind::For => write!(f, "For"),
             TokenKind::If => write!(f, "If"),
+            TokenKind::Import => write!(f, "Import"),
             TokenKind::Nil => write!(f, "Nil"),
             TokenKind::Or => write!(f, "Or"),
             TokenKind::Print => write!(f, "Print"),
             TokenKind::Return => write!(f, "Return"),
             TokenKind::Super => write!(f, "Super"),
+            TokenKind::Switch => write!(f, "Switch"),
             TokenKind::This => write!(f, "This"),
+            TokenKind::Throw => write!(f, "Throw"),
             TokenKind::True => write!(f, "True"),
+            TokenKind::Try => write!(f, "Try"),
             TokenKind::Var => write!(f, "Var"),
             TokenKind::While => write!(f, "While"),
             TokenKind::Comment => write!(f, "Comment"),
             TokenKind::NewLine => write!(f, "NewLine"),
             TokenKind::WhiteSpace => write!(f, "WhiteSpace"),
+            TokenKind::Eof => write!(f, "Eof"),
         }
     }
 }
+/// the literal value a token carries, string and number tokens carry their
+/// value and identifiers carry their interned name, the rest of the tokens
+/// have `Literal::None`
+#[derive(Clone)]
+pub enum Literal {
+    Number(f64),
+    Str(Rc<str>),
+    Identifier(Rc<str>),
+    None,
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Number(n) => write!(f, "{}", n),
+            Literal::Str(s) | Literal::Identifier(s) => write!(f, "{}", s),
+            Literal::None => Ok(()),
+        }
+    }
+}
+
+/// replaces the escape sequences in the given string content (without the quotes)
+/// with the characters they represent, on invalid escape sequence returns the byte
+/// offset of the backslash in `raw` and the invalid escape sequence itself
+fn unescape(raw: &str) -> Result<Cow<'_, str>, (usize, String)> {
+    // most strings have no escapes, those are returned without copying them
+    if !raw.contains('\\') {
+        return Ok(Cow::Borrowed(raw));
+    }
+
+    let mut result = String::with_capacity(raw.len());
+    let mut chars = raw.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some((_, 'n')) => result.push('\n'),
+            Some((_, 't')) => result.push('\t'),
+            Some((_, '"')) => result.push('"'),
+            Some((_, '\\')) => result.push('\\'),
+            Some((_, '$')) => result.push('$'),
+            Some((_, 'u')) => {
+                // unicode escape is in the form of `\u{XXXX}` where `XXXX` is
+                // 1 to 6 hex digits of a valid unicode scalar value
+                let end = raw[i..].find('}').map(|end| i + end);
+                let escape = match end {
+                    Some(end) if raw[i..].starts_with("\\u{") => &raw[i..=end],
+                    _ => return Err((i, "\\u".to_string())),
+                };
+
+                let digits = &escape[3..escape.len() - 1];
+                // `from_str_radix` accepts a leading `+`, so the digits are checked first
+                let value = match digits.len() {
+                    1..=6 if digits.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                        u32::from_str_radix(digits, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                    }
+                    _ => None,
+                };
+
+                match value {
+                    Some(value) => result.push(value),
+                    None => return Err((i, escape.to_string())),
+                }
+
+                // skip the rest of the escape sequence, `\u` was already consumed
+                while chars.next_if(|(j, _)| *j < i + escape.len()).is_some() {}
+            }
+            Some((_, c)) => return Err((i, format!("\\{}", c))),
+            None => return Err((i, "\\".to_string())),
+        }
+    }
+    Ok(Cow::Owned(result))
+}
+
+/// returns the offset of the first byte in `haystack` that is one of the `needles`,
+/// like `memchr` the haystack is compared 8 bytes at a time, and only the 8 bytes
+/// with a match are searched byte by byte
+fn find_byte<const N: usize>(haystack: &[u8], needles: [u8; N]) -> Option<usize> {
+    const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+    const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+    let mut offset = 0;
+    for chunk in haystack.chunks_exact(8) {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        // a byte of `x` is zero where the word has the needle, the expression
+        // sets the high bit of the first zero byte of `x`
+        let found = needles.iter().any(|&needle| {
+            let x = word ^ (LOW_BITS * needle as u64);
+            x.wrapping_sub(LOW_BITS) & !x & HIGH_BITS != 0
+        });
+        if found {
+            break;
+        }
+        offset += 8;
+    }
+
+    haystack[offset..]
+        .iter()
+        .position(|byte| needles.contains(byte))
+        .map(|i| offset + i)
+}
+
+/// returns the line and column the position will be at after consuming
+/// the given text, columns are counted in characters and not bytes
+fn position_after(line: u32, column: u32, text: &str) -> (u32, u32) {
+    match text.rfind('\n') {
+        Some(i) => (
+            line + text.matches('\n').count() as u32,
+            text[i + 1..].chars().count() as u32 + 1,
+        ),
+        None => (line, column + text.chars().count() as u32),
+    }
+}
+
+/// a range of the source, `start` and `end` are byte offsets and `line`
+/// is the line the range starts at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: u32,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: u32) -> Span {
+        Span { start, end, line }
+    }
+
+    /// returns a span from the start of this span to the end of the other span
+    pub fn to(&self, other: &Span) -> Span {
+        Span::new(self.start, other.end, self.line)
+    }
+}
+
+/// a token does not own its lexeme, it only keeps the byte range of the lexeme in
+/// the scanned source, use `Token::lexeme` with the same source to get the lexeme
 pub struct Token {
     kind: TokenKind,
-    lexeme: String,
-    literal: String,
+    literal: Literal,
     line: u32,
+    column: u32,
+    start: usize,
+    end: usize,
 }
 
 impl Token {
-    pub fn new(kind: TokenKind, lexeme: String, literal: String, line: u32) -> Token {
+    pub fn new(
+        kind: TokenKind,
+        literal: Literal,
+        line: u32,
+        column: u32,
+        start: usize,
+        end: usize,
+    ) -> Token {
         Token {
             kind,
-            lexeme,
             literal,
             line,
+            column,
+            start,
+            end,
         }
     }
 
     pub fn kind(&self) -> TokenKind {
         self.kind.clone()
     }
+
+    /// returns the lexeme of the token from the source the token was scanned from
+    pub fn lexeme<'a>(&self, source: &'a [u8]) -> &'a str {
+        std::str::from_utf8(&source[self.start..self.end]).expect("token lexeme is not valid utf8")
+    }
+
+    pub fn literal(&self) -> &Literal {
+        &self.literal
+    }
+
+    /// the line the token starts at
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// the column (1 based) the token starts at
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// byte offset of the first byte of the token in the source
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// byte offset one past the last byte of the token in the source
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.end, self.line)
+    }
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} `{}` {}", self.kind, self.lexeme, self.literal)
+        match self.literal {
+            Literal::None => write!(f, "{}", self.kind),
+            _ => write!(f, "{} {}", self.kind, self.literal),
+        }
     }
 }
 
-pub struct Scanner {
-    content: Vec<u8>,
+pub struct Scanner<'a> {
+    content: &'a [u8],
     current: usize,
     start: usize,
     line: u32,
+    column: u32,
+    finished: bool,
+    unicode_identifiers: bool,
+    skip_trivia: bool,
+    // string values and identifiers are interned, so a string or a name
+    // repeated in the source is allocated once
+    interner: Interner,
+    // every interpolated expression we are in, the last item is the
+    // innermost interpolation
+    interpolations: Vec<OpenInterpolation>,
 }
 
-impl Scanner {
-    pub fn new(content: Vec<u8>) -> Scanner {
+/// an interpolated expression `${` the scanner is in
+struct OpenInterpolation {
+    // the number of braces opened inside the expression that are not closed yet
+    braces: usize,
+    // the position of the `$` that opened the interpolation
+    start: usize,
+    line: u32,
+    column: u32,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(content: &'a [u8]) -> Scanner<'a> {
         Scanner {
             content,
             current: 0,
             start: 0,
             line: 1,
+            column: 1,
+            finished: false,
+            unicode_identifiers: false,
+            skip_trivia: false,
+            interner: Interner::new(),
+            interpolations: Vec::new(),
+        }
+    }
+
+    /// allow identifiers to contain unicode letters and digits, by default
+    /// only ascii letters, digits and `_` are allowed
+    pub fn unicode_identifiers(mut self, enabled: bool) -> Scanner<'a> {
+        self.unicode_identifiers = enabled;
+        self
+    }
+
+    /// intern the string values and identifiers with the given interner, by
+    /// default every scanner has its own interner
+    pub fn interner(mut self, interner: Interner) -> Scanner<'a> {
+        self.interner = interner;
+        self
+    }
+
+    /// skip whitespace, newline and comment tokens instead of returning them,
+    /// by default every token is returned
+    pub fn skip_trivia(mut self, enabled: bool) -> Scanner<'a> {
+        self.skip_trivia = enabled;
+        self
+    }
+
+    /// returns the current byte without consuming it, `None` when
+    /// there are no more bytes to read
+    fn peek(&self) -> Option<u8> {
+        self.content.get(self.current).copied()
+    }
+
+    /// returns the byte after the current byte without consuming anything,
+    /// `None` when that byte is past the end of the content
+    fn peek_next(&self) -> Option<u8> {
+        self.content.get(self.current + 1).copied()
+    }
+
+    /// decodes the utf8 character at the current position without consuming it,
+    /// returns `None` when there are no more bytes to read and an error if the
+    /// bytes at the current position are not valid utf8
+    fn peek_char(&self) -> Option<Result<char, LoxErrorType>> {
+        let byte = self.peek()?;
+        let width = match byte {
+            0x00..=0x7f => 1,
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => return Some(Err(LoxErrorType::InvalidUtf8)),
+        };
+
+        let bytes = self.content.get(self.current..self.current + width);
+        match bytes.and_then(|bytes| std::str::from_utf8(bytes).ok()) {
+            Some(s) => s.chars().next().map(Ok),
+            None => Some(Err(LoxErrorType::InvalidUtf8)),
+        }
+    }
+
+    /// consumes and returns the current byte
+    fn advance(&mut self) -> Option<u8> {
+        let byte = self.peek();
+        if byte.is_some() {
+            self.current += 1;
+        }
+        byte
+    }
+
+    /// consumes the current byte only if it is the expected byte, returns
+    /// if the byte was consumed
+    fn advance_if(&mut self, expected: u8) -> bool {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// consumes bytes until the next byte that is one of the `needles`, returns
+    /// that byte without consuming it, `None` if the end was reached instead
+    fn advance_until<const N: usize>(&mut self, needles: [u8; N]) -> Option<u8> {
+        match find_byte(&self.content[self.current..], needles) {
+            Some(offset) => {
+                self.current += offset;
+                self.peek()
+            }
+            None => {
+                self.current = self.content.len();
+                None
+            }
+        }
+    }
+
+    /// consumes bytes while the given predicate holds for the current byte
+    fn advance_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        while self.peek().is_some_and(&predicate) {
+            self.current += 1;
+        }
+    }
+
+    /// scans a single token starting at `self.start`, on success the cursor
+    /// is placed after the token and the token kind is returned, on error the
+    /// cursor is placed after the invalid input so scanning can continue
+    fn scan_token(&mut self) -> Result<TokenKind, LoxErrorType> {
+        // the caller makes sure there is at least one byte left
+        let byte = self.advance().unwrap();
+
+        match byte {
+            b'\r' | b'\t' | b' ' => {
+                self.advance_while(|c| matches!(c, b'\r' | b'\t' | b' '));
+                Ok(TokenKind::WhiteSpace)
+            }
+            b'\n' => Ok(TokenKind::NewLine),
+            b'(' => Ok(TokenKind::LeftParen),
+            b')' => Ok(TokenKind::RightParen),
+            b'{' => {
+                // braces inside an interpolated expression are counted, so only
+                // the brace that closes the interpolation resumes the string
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.braces += 1;
+                }
+                Ok(TokenKind::LeftBrace)
+            }
+            b'}' => match self.interpolations.last_mut() {
+                Some(interpolation) if interpolation.braces == 0 => {
+                    self.interpolations.pop();
+                    self.string()
+                }
+                Some(interpolation) => {
+                    interpolation.braces -= 1;
+                    Ok(TokenKind::RightBrace)
+                }
+                None => Ok(TokenKind::RightBrace),
+            },
+            b'[' => Ok(TokenKind::LeftBracket),
+            b']' => Ok(TokenKind::RightBracket),
+            b',' => Ok(TokenKind::Comma),
+            b':' => Ok(TokenKind::Colon),
+            b'?' => Ok(TokenKind::Question),
+            b'.' => Ok(TokenKind::Dot),
+            b';' => Ok(TokenKind::Semicolon),
+            b'-' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::MinusEqual)
+                } else {
+                    Ok(TokenKind::Minus)
+                }
+            }
+            b'+' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::PlusEqual)
+                } else {
+                    Ok(TokenKind::Plus)
+                }
+            }
+            b'*' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::StarEqual)
+                } else if self.advance_if(b'*') {
+                    Ok(TokenKind::StarStar)
+                } else {
+                    Ok(TokenKind::Star)
+                }
+            }
+            b'%' => Ok(TokenKind::Percent),
+            b'&' => Ok(TokenKind::Ampersand),
+            b'|' => Ok(TokenKind::Pipe),
+            b'^' => Ok(TokenKind::Caret),
+            b'=' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::EqualEqual)
+                } else {
+                    Ok(TokenKind::Equal)
+                }
+            }
+            b'>' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::GreaterEqual)
+                } else if self.advance_if(b'>') {
+                    Ok(TokenKind::GreaterGreater)
+                } else {
+                    Ok(TokenKind::Greater)
+                }
+            }
+            b'<' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::LessEqual)
+                } else if self.advance_if(b'<') {
+                    Ok(TokenKind::LessLess)
+                } else {
+                    Ok(TokenKind::Less)
+                }
+            }
+            b'!' => {
+                if self.advance_if(b'=') {
+                    Ok(TokenKind::BangEqual)
+                } else {
+                    Ok(TokenKind::Bang)
+                }
+            }
+            b'/' => {
+                if self.advance_if(b'/') {
+                    self.advance_until([b'\n']);
+                    Ok(TokenKind::Comment)
+                } else if self.advance_if(b'*') {
+                    self.block_comment()
+                } else if self.advance_if(b'=') {
+                    Ok(TokenKind::SlashEqual)
+                } else {
+                    Ok(TokenKind::Slash)
+                }
+            }
+            b'"' => self.string(),
+            b'0'..=b'9' => Ok(self.number()),
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => Ok(self.identifier()),
+            c if c.is_ascii() => Err(LoxErrorType::UnexpectedCharacter(c.into())),
+            _ => {
+                // non ascii byte, decode the whole character it starts, since
+                // we already consumed the first byte, we step back before decoding
+                self.current -= 1;
+                match self.peek_char().unwrap() {
+                    Ok(c) => {
+                        self.current += c.len_utf8();
+                        if self.unicode_identifiers && c.is_alphabetic() {
+                            Ok(self.identifier())
+                        } else {
+                            Err(LoxErrorType::UnexpectedCharacter(c))
+                        }
+                    }
+                    Err(e) => {
+                        self.current += 1;
+                        Err(e)
+                    }
+                }
+            }
+        }
+    }
+
+    /// scans the rest of a block comment, block comments can be nested, so we keep
+    /// track of the nesting depth and only finish when we close the outer comment
+    fn block_comment(&mut self) -> Result<TokenKind, LoxErrorType> {
+        let mut depth = 1usize;
+
+        // only `/` and `*` can open or close a comment, so the bytes between them are skipped
+        while let Some(byte) = self.advance_until([b'/', b'*']) {
+            self.current += 1;
+            match (byte, self.peek()) {
+                (b'/', Some(b'*')) => {
+                    self.current += 1;
+                    depth += 1;
+                }
+                (b'*', Some(b'/')) => {
+                    self.current += 1;
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return Ok(TokenKind::Comment);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(LoxErrorType::UnterminatedComment)
+    }
+
+    /// scans the rest of a string literal, strings may contain any utf8 characters,
+    /// only the bytes that end the string or start an escape or interpolation are
+    /// looked at, the utf8 of the whole string is validated with the lexeme
+    fn string(&mut self) -> Result<TokenKind, LoxErrorType> {
+        while let Some(byte) = self.advance_until([b'"', b'\\', b'$']) {
+            self.current += 1;
+
+            match byte {
+                // a backslash escapes the next byte, so an escaped `"` does
+                // not terminate the string, the escape itself is validated
+                // later by `unescape`, utf8 continuation bytes are never one
+                // of the bytes we look for, so skipping a single byte is enough
+                b'\\' => {
+                    self.advance();
+                }
+                b'"' => return Ok(TokenKind::String),
+                // `${` starts an interpolated expression, the string segment ends
+                // here and the scanner continues with the expression tokens until
+                // the matching `}`, which resumes the string
+                b'$' if self.peek() == Some(b'{') => {
+                    // the position of the `$` is kept, so an unterminated
+                    // interpolation can be reported where it was opened
+                    let before =
+                        String::from_utf8_lossy(&self.content[self.start..self.current - 1]);
+                    let (line, column) = position_after(self.line, self.column, &before);
+
+                    self.interpolations.push(OpenInterpolation {
+                        braces: 0,
+                        start: self.current - 1,
+                        line,
+                        column,
+                    });
+                    self.current += 1;
+                    return Ok(TokenKind::Interpolation);
+                }
+                _ => {}
+            }
+        }
+        Err(LoxErrorType::UnterminatedString)
+    }
+
+    /// scans the rest of a number literal, a number may contain a single
+    /// floating point which must be followed by a digit
+    fn number(&mut self) -> TokenKind {
+        self.advance_while(|c| c.is_ascii_digit());
+
+        if self.peek() == Some(b'.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            self.current += 1;
+            self.advance_while(|c| c.is_ascii_digit());
+        }
+        TokenKind::Number
+    }
+
+    /// scans the rest of an identifier and checks if it is a keyword
+    fn identifier(&mut self) -> TokenKind {
+        if self.unicode_identifiers {
+            while let Some(Ok(c)) = self.peek_char() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                self.current += c.len_utf8();
+            }
+        } else {
+            self.advance_while(|c| c.is_ascii_alphanumeric() || c == b'_');
+        }
+
+        let identifier = std::str::from_utf8(&self.content[self.start..self.current]).unwrap();
+        match KEYWORDS.get(identifier) {
+            Some(t) => t.clone(),
+            None => TokenKind::Identifier,
         }
     }
 }
 
-impl Iterator for Scanner {
+impl Iterator for Scanner<'_> {
     type Item = Result<Token, LoxError>;
 
     /// returns the next available token, in case of error, return
     /// a `LoxError`, the iterator will return `None` when there are no
     /// more tokens to process
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current >= self.content.len() {
-            return None;
-        }
+        // trivia tokens that are skipped are never built, the loop only
+        // moves the position past them and scans the next token
+        loop {
+            if self.finished {
+                return None;
+            }
 
-        let content_slice = &self.content[self.current..];
-
-        match TokenKind::from_utf8(content_slice) {
-            Ok((token_type, token_size)) => {
-                // get the lexeme string based on the returned `token_size`
-                let lexeme =
-                    unsafe { String::from_utf8_unchecked(content_slice[..token_size].to_vec()) };
-
-                // update the current (cursor) to point to the next char
-                // based on the token size
-                self.current += token_size;
-
-                // some tokens have special meaning to the scanner, in
-                // this match case we handle those special cases
-                match token_type {
-                    TokenKind::NewLine => self.line += 1,
-                    TokenKind::String => {
-                        // since lox supports multi line strings, we need to couldn't how many
-                        // new lines there are in the `lexeme` and update the scanner `line`
-                        // property
-                        let new_lines = lexeme.chars().filter(|c| *c == '\n').count();
-                        self.line += new_lines as u32;
+            self.start = self.current;
+
+            // the end of the content is marked with an explicit `Eof` token, after
+            // that the iterator is done
+            if self.current >= self.content.len() {
+                // an interpolated expression that was never closed, means the
+                // string it is in was never terminated either
+                if let Some(interpolation) = self.interpolations.first() {
+                    let error = LoxError::new(
+                        interpolation.line,
+                        interpolation.column,
+                        interpolation.start,
+                        interpolation.start + 2,
+                        LoxErrorType::UnterminatedInterpolation,
+                    );
+                    self.interpolations.clear();
+                    return Some(Err(error));
+                }
+
+                self.finished = true;
+                let token = Token::new(
+                    TokenKind::Eof,
+                    Literal::None,
+                    self.line,
+                    self.column,
+                    self.current,
+                    self.current,
+                );
+                return Some(Ok(token));
+            }
+
+            match self.scan_token() {
+                Ok(token_type) => {
+                    // the token position is where the token started, so we save it
+                    // before updating the line and column
+                    let line = self.line;
+                    let column = self.column;
+
+                    // comments may contain any bytes, so the lexeme must be validated
+                    // before we can use it as a string
+                    let bytes = &self.content[self.start..self.current];
+                    let lexeme = match std::str::from_utf8(bytes) {
+                        Ok(lexeme) => lexeme,
+                        Err(e) => {
+                            let valid = std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap();
+                            let (error_line, error_column) = position_after(line, column, valid);
+
+                            // the error points at the invalid bytes only, an incomplete
+                            // sequence at the end of the source has no error length
+                            let error_start = self.start + e.valid_up_to();
+                            let error_len = e.error_len().unwrap_or(bytes.len() - e.valid_up_to());
+
+                            let skipped = String::from_utf8_lossy(bytes);
+                            (self.line, self.column) = position_after(line, column, &skipped);
+                            return Some(Err(LoxError::new(
+                                error_line,
+                                error_column,
+                                error_start,
+                                error_start + error_len,
+                                LoxErrorType::InvalidUtf8,
+                            )));
+                        }
+                    };
+
+                    // since lox supports multi line strings and block comments, the
+                    // position may move multiple lines
+                    (self.line, self.column) = position_after(line, column, lexeme);
+
+                    if self.skip_trivia && token_type.is_trivia() {
+                        continue;
                     }
-                    _ => {}
+
+                    // string and number tokens carry their value, so the parser
+                    // wont have to parse the lexeme again, a string segment starts after
+                    // a `"` or the `}` that closed an interpolation, and ends before a `"`
+                    // or the `${` that opens an interpolation
+                    let literal = match token_type {
+                        TokenKind::String | TokenKind::Interpolation => {
+                            let end = match token_type {
+                                TokenKind::String => lexeme.len() - 1,
+                                _ => lexeme.len() - 2,
+                            };
+                            match unescape(&lexeme[1..end]) {
+                                Ok(value) => Literal::Str(self.interner.intern(&value)),
+                                Err((offset, escape)) => {
+                                    // the offset is relative to the segment content, we add 1 for
+                                    // the opening `"` or `}` and find the exact line and column of the
+                                    // escape, since the string may span multiple lines
+                                    let (escape_line, escape_column) =
+                                        position_after(line, column, &lexeme[..offset + 1]);
+                                    let escape_start = self.start + offset + 1;
+                                    return Some(Err(LoxError::new(
+                                        escape_line,
+                                        escape_column,
+                                        escape_start,
+                                        escape_start + escape.len(),
+                                        LoxErrorType::InvalidEscape(escape),
+                                    )));
+                                }
+                            }
+                        }
+                        TokenKind::Number => Literal::Number(lexeme.parse().unwrap()),
+                        TokenKind::Identifier => Literal::Identifier(self.interner.intern(lexeme)),
+                        _ => Literal::None,
+                    };
+
+                    let token =
+                        Token::new(token_type, literal, line, column, self.start, self.current);
+                    return Some(Ok(token));
+                }
+                Err(error_type) => {
+                    let error =
+                        LoxError::new(self.line, self.column, self.start, self.current, error_type);
+
+                    // skip the invalid input, so the next call continues after it
+                    let skipped = String::from_utf8_lossy(&self.content[self.start..self.current]);
+                    (self.line, self.column) = position_after(self.line, self.column, &skipped);
+                    return Some(Err(error));
                 }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the kinds of the non trivia tokens of the source, the source must scan without errors
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        Scanner::new(source.as_bytes())
+            .skip_trivia(true)
+            .map(|token| token.unwrap().kind())
+            .collect()
+    }
+
+    #[test]
+    fn source_ending_with_a_possible_two_character_token() {
+        use TokenKind::*;
+
+        assert_eq!(kinds("a ="), [Identifier, Equal, Eof]);
+        assert_eq!(kinds("<"), [Less, Eof]);
+        assert_eq!(kinds(">"), [Greater, Eof]);
+        assert_eq!(kinds("!"), [Bang, Eof]);
+        assert_eq!(kinds("/"), [Slash, Eof]);
+        assert_eq!(kinds("a <="), [Identifier, LessEqual, Eof]);
+    }
+
+    #[test]
+    fn operator_tokens() {
+        use TokenKind::*;
+
+        assert_eq!(kinds("a % b"), [Identifier, Percent, Identifier, Eof]);
+        assert_eq!(
+            kinds("+= -= *= /="),
+            [PlusEqual, MinusEqual, StarEqual, SlashEqual, Eof]
+        );
+        // `/=`, `//` and `/*` all start with the same byte
+        assert_eq!(
+            kinds("a /= b // c /= d"),
+            [Identifier, SlashEqual, Identifier, Eof]
+        );
+        assert_eq!(kinds("/* /= */ / ="), [Slash, Equal, Eof]);
+        assert_eq!(
+            kinds("& | ^ << >>"),
+            [Ampersand, Pipe, Caret, LessLess, GreaterGreater, Eof]
+        );
+        // there are no shift assignments, `<<=` is a shift followed by `=`
+        assert_eq!(kinds("<<="), [LessLess, Equal, Eof]);
+        assert_eq!(kinds(">= >>"), [GreaterEqual, GreaterGreater, Eof]);
+        assert_eq!(kinds("* ** *="), [Star, StarStar, StarEqual, Eof]);
+        assert_eq!(kinds("**="), [StarStar, Equal, Eof]);
+        assert_eq!(
+            kinds("[1][0]"),
+            [
+                LeftBracket,
+                Number,
+                RightBracket,
+                LeftBracket,
+                Number,
+                RightBracket,
+                Eof
+            ]
+        );
+        assert_eq!(
+            kinds("x ? y : z"),
+            [Identifier, Question, Identifier, Colon, Identifier, Eof]
+        );
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        use TokenKind::*;
+
+        assert_eq!(kinds("/* a /* b */ c */ x"), [Identifier, Eof]);
+
+        // the newlines inside the comment are counted
+        let source = b"/* a\n /* b\n */ c */ x";
+        let x = Scanner::new(source)
+            .skip_trivia(true)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!((x.line(), x.column()), (3, 10));
+    }
+
+    #[test]
+    fn from_is_not_reserved() {
+        use TokenKind::*;
+
+        // `import ... from` will match `from` by its name, so it stays a valid name
+        assert_eq!(
+            kinds("var from = 1;"),
+            [Var, Identifier, Equal, Number, Semicolon, Eof]
+        );
+    }
+
+    #[test]
+    fn eof_is_the_last_token() {
+        assert_eq!(kinds(""), [TokenKind::Eof]);
+
+        let mut scanner = Scanner::new(b"1");
+        assert_eq!(scanner.next().unwrap().unwrap().kind(), TokenKind::Number);
+        assert_eq!(scanner.next().unwrap().unwrap().kind(), TokenKind::Eof);
+        assert!(scanner.next().is_none());
+    }
+
+    #[test]
+    fn positions_count_characters_and_offsets_count_bytes() {
+        let source = "\"é\" + \"x\ny\" d";
+        let positions: Vec<(u32, u32, usize, usize)> = Scanner::new(source.as_bytes())
+            .skip_trivia(true)
+            .map(|token| {
+                let token = token.unwrap();
+                let span = token.span();
+                (token.line(), token.column(), span.start, span.end)
+            })
+            .collect();
+
+        assert_eq!(
+            positions,
+            [
+                (1, 1, 0, 4),
+                (1, 5, 5, 6),
+                // the string spans two lines, so the token after it is on the second line
+                (1, 7, 7, 12),
+                (2, 4, 13, 14),
+                (2, 5, 14, 14),
+            ]
+        );
+    }
+
+    /// the line, column and byte offsets of the first error of the source
+    fn error_position(source: &[u8]) -> (u32, u32, usize, usize) {
+        let error = Scanner::new(source)
+            .find_map(Result::err)
+            .expect("the source has an error");
+        (error.line(), error.column(), error.start(), error.end())
+    }
+
+    #[test]
+    fn errors_carry_the_offsets_they_point_at() {
+        assert_eq!(error_position(b"a @"), (1, 3, 2, 3));
+        // an unexpected character spans all of its bytes
+        assert_eq!(error_position("é".as_bytes()), (1, 1, 0, 2));
+        assert_eq!(error_position(b"1 \"ab"), (1, 3, 2, 5));
+        assert_eq!(error_position(b"/* a\n*"), (1, 1, 0, 6));
+        assert_eq!(error_position(b"\"a\\qb\""), (1, 3, 2, 4));
+        assert_eq!(error_position(b"\"a\\u{+41}\""), (1, 3, 2, 9));
+        assert_eq!(error_position(b"// a\xffb"), (1, 5, 4, 5));
+        assert_eq!(error_position(b"x = \"a ${b"), (1, 8, 7, 9));
+    }
+
+    #[test]
+    fn position_after_text() {
+        assert_eq!(position_after(1, 1, "abc"), (1, 4));
+        assert_eq!(position_after(1, 1, "éü"), (1, 3));
+        assert_eq!(position_after(3, 7, "a\nbé\nxyz"), (5, 4));
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        use TokenKind::*;
+
+        let source = "var é_1 = ñame;".as_bytes();
+        let errors = Scanner::new(source).filter(Result::is_err).count();
+        assert_eq!(errors, 2);
+
+        let tokens: Vec<Token> = Scanner::new(source)
+            .unicode_identifiers(true)
+            .skip_trivia(true)
+            .map(Result::unwrap)
+            .collect();
+        let kinds: Vec<TokenKind> = tokens.iter().map(Token::kind).collect();
+        assert_eq!(kinds, [Var, Identifier, Equal, Identifier, Semicolon, Eof]);
+        assert_eq!(tokens[1].lexeme(source), "é_1");
+        assert_eq!(tokens[3].lexeme(source), "ñame");
+    }
+
+    /// the kind and literal of every non trivia token, the source must scan without errors
+    fn literals(source: &str) -> Vec<(TokenKind, String)> {
+        Scanner::new(source.as_bytes())
+            .skip_trivia(true)
+            .map(|token| {
+                let token = token.unwrap();
+                (token.kind(), token.literal().to_string())
+            })
+            .collect()
+    }
+
+    fn segment(kind: TokenKind, literal: &str) -> (TokenKind, String) {
+        (kind, literal.to_string())
+    }
+
+    #[test]
+    fn interpolation_segments() {
+        use TokenKind::*;
+
+        assert_eq!(
+            literals("\"a ${b} c\""),
+            [
+                segment(Interpolation, "a "),
+                segment(Identifier, "b"),
+                segment(String, " c"),
+                segment(Eof, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn interpolation_with_nested_braces() {
+        use TokenKind::*;
+
+        // only the brace that closes the interpolation resumes the string
+        assert_eq!(
+            literals("\"x${ {a: {}} }y\""),
+            [
+                segment(Interpolation, "x"),
+                segment(LeftBrace, ""),
+                segment(Identifier, "a"),
+                segment(Colon, ""),
+                segment(LeftBrace, ""),
+                segment(RightBrace, ""),
+                segment(RightBrace, ""),
+                segment(String, "y"),
+                segment(Eof, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn interpolation_with_nested_strings() {
+        use TokenKind::*;
+
+        assert_eq!(
+            literals("\"a ${\"b\" + \"${c}\"} d\""),
+            [
+                segment(Interpolation, "a "),
+                segment(String, "b"),
+                segment(Plus, ""),
+                segment(Interpolation, ""),
+                segment(Identifier, "c"),
+                segment(String, ""),
+                segment(String, " d"),
+                segment(Eof, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_interpolation() {
+        assert_eq!(
+            literals("\"\\${x}\""),
+            [
+                segment(TokenKind::String, "${x}"),
+                segment(TokenKind::Eof, "")
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_are_replaced_in_the_literal() {
+        assert_eq!(
+            literals(r#""\n\t\"\\\u{41}\u{1F600}""#),
+            [
+                segment(TokenKind::String, "\n\t\"\\A\u{1F600}"),
+                segment(TokenKind::Eof, "")
+            ]
+        );
+
+        // a unicode escape without its closing brace
+        let errors: Vec<String> = Scanner::new(br#""\u{41""#)
+            .filter_map(Result::err)
+            .map(|e| e.kind().to_string())
+            .collect();
+        assert_eq!(errors, ["Invalid escape sequence `\\u`."]);
+    }
+
+    #[test]
+    fn repeated_strings_and_names_share_one_allocation() {
+        let interned = |token: &Token| match token.literal() {
+            Literal::Str(s) | Literal::Identifier(s) => Rc::clone(s),
+            _ => panic!("token has no interned value"),
+        };
+
+        let interner = Interner::new();
+        let tokens: Vec<Token> = Scanner::new(b"a \"s\" a \"s\"")
+            .interner(interner.clone())
+            .skip_trivia(true)
+            .map(Result::unwrap)
+            .collect();
+        assert!(Rc::ptr_eq(&interned(&tokens[0]), &interned(&tokens[2])));
+        assert!(Rc::ptr_eq(&interned(&tokens[1]), &interned(&tokens[3])));
+        assert_eq!(interner.len(), 2);
+
+        // a scanner of another source with the same interner gets the same names
+        let other = Scanner::new(b"a")
+            .interner(interner.clone())
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(Rc::ptr_eq(&interned(&tokens[0]), &interned(&other)));
+    }
+
+    /// the byte by byte search `find_byte` must agree with
+    fn find_byte_slow(haystack: &[u8], needles: &[u8]) -> Option<usize> {
+        haystack.iter().position(|byte| needles.contains(byte))
+    }
+
+    #[test]
+    fn find_byte_in_every_lane_and_in_the_tail() {
+        // lengths up to 3 full chunks plus a tail, the needle at every position
+        for len in 0..=27 {
+            for at in 0..len {
+                let mut haystack = vec![b'a'; len];
+                haystack[at] = b'"';
+                assert_eq!(find_byte(&haystack, [b'"']), Some(at), "len {len} at {at}");
+                assert_eq!(find_byte(&haystack, [b'$', b'"', b'\\']), Some(at));
+            }
+            assert_eq!(find_byte(&vec![b'a'; len], [b'"']), None, "len {len}");
+        }
+    }
+
+    #[test]
+    fn find_byte_matches_a_byte_by_byte_search() {
+        // bytes around the needles and with the high bit set, which the
+        // word at a time comparison must not mistake for a needle
+        let alphabet = [
+            0x00, 0x01, b'\t', b'\n', b'\x0b', b'"', b'#', b'$', 0x7f, 0x80, 0xc3, 0xff,
+        ];
+        let needle_sets: [&[u8]; 3] = [b"\n", b"\"\\$", &[0xff, 0x00]];
+
+        let mut state = 0x2545_f491_u32;
+        for _ in 0..2000 {
+            let len = (state % 40) as usize;
+            let haystack: Vec<u8> = (0..len)
+                .map(|_| {
+                    // xorshift, a fixed seed keeps the test deterministic
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    alphabet[state as usize % alphabet.len()]
+                })
+                .collect();
 
-                let token = Token::new(token_type, lexeme, String::new(), self.line);
-                Some(Ok(token))
+            for needles in needle_sets {
+                let expected = find_byte_slow(&haystack, needles);
+                let actual = match needles {
+                    [a] => find_byte(&haystack, [*a]),
+                    [a, b] => find_byte(&haystack, [*a, *b]),
+                    [a, b, c] => find_byte(&haystack, [*a, *b, *c]),
+                    _ => unreachable!(),
+                };
+                assert_eq!(actual, expected, "{haystack:?} {needles:?}");
             }
-            Err(error_type) => Some(Err(LoxError::new(self.line, error_type))),
         }
     }
 }
@@ -0,0 +1,73 @@
+//! golden-file tests for the rendered diagnostics, every `.lox` fixture in
+//! `tests/diagnostics` has a companion `.stderr` file with the diagnostics the
+//! fixture is expected to produce, run the tests with `UPDATE_GOLDEN=1` to
+//! regenerate the `.stderr` files after an intended change
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use jlox::render::{ColorChoice, Renderer};
+use jlox::scanner::Scanner;
+
+/// renders every diagnostic produced for the given source without colors,
+/// the location line only has the file name, so the goldens do not depend
+/// on where the repository is checked out
+fn render_diagnostics(name: &str, source: &[u8]) -> String {
+    let renderer = Renderer::new(name, source, ColorChoice::Never);
+    Scanner::new(source)
+        .filter_map(Result::err)
+        .map(|e| renderer.render(&e))
+        .collect()
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/diagnostics");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+#[test]
+fn diagnostics_match_golden_files() {
+    let update = env::var("UPDATE_GOLDEN").is_ok_and(|value| value == "1");
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no diagnostic fixtures were found");
+
+    let mut mismatches = Vec::new();
+    for fixture in fixtures {
+        let name = fixture.file_name().unwrap().to_string_lossy();
+        let actual = render_diagnostics(&name, &fs::read(&fixture).unwrap());
+        let golden = fixture.with_extension("stderr");
+
+        if update {
+            fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&golden).unwrap_or_else(|_| {
+            panic!(
+                "missing golden file `{}`, run with `UPDATE_GOLDEN=1` to create it",
+                golden.display()
+            )
+        });
+        if expected != actual {
+            mismatches.push(format!(
+                "`{}`\n--- expected\n{}--- actual\n{}",
+                fixture.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "diagnostics do not match the golden files, run with `UPDATE_GOLDEN=1` \
+         to accept the changes\n\n{}",
+        mismatches.join("\n")
+    );
+}